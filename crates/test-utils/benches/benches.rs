@@ -1,7 +1,7 @@
 use associative_cache::*;
 use criterion::*;
 
-fn run_bench<C: Capacity, I: Indices<*mut u64, C>>(c: &mut Criterion, name: &str) {
+fn run_bench<C: Capacity, I: Default + Indices<*mut u64, C>>(c: &mut Criterion, name: &str) {
     let elems = C::CAPACITY;
 
     {
@@ -69,12 +69,26 @@ define_benches! {
     hash_sixteen_way_512(Capacity512, HashSixteenWay);
     hash_thirty_two_way_512(Capacity512, HashThirtyTwoWay);
 
+    hash_with_direct_mapped_512(Capacity512, HashDirectMappedWith);
+    hash_with_two_way_512(Capacity512, HashTwoWayWith);
+    hash_with_four_way_512(Capacity512, HashFourWayWith);
+    hash_with_eight_way_512(Capacity512, HashEightWayWith);
+    hash_with_sixteen_way_512(Capacity512, HashSixteenWayWith);
+    hash_with_thirty_two_way_512(Capacity512, HashThirtyTwoWayWith);
+
     pointer_direct_mapped_512(Capacity512, PointerDirectMapped);
     pointer_two_way_512(Capacity512, PointerTwoWay);
     pointer_four_way_512(Capacity512, PointerFourWay);
     pointer_eight_way_512(Capacity512, PointerEightWay);
     pointer_sixteen_way_512(Capacity512, PointerSixteenWay);
     pointer_thirty_two_way_512(Capacity512, PointerThirtyTwoWay);
+
+    fibonacci_direct_mapped_512(Capacity512, FibonacciDirectMapped);
+    fibonacci_two_way_512(Capacity512, FibonacciTwoWay);
+    fibonacci_four_way_512(Capacity512, FibonacciFourWay);
+    fibonacci_eight_way_512(Capacity512, FibonacciEightWay);
+    fibonacci_sixteen_way_512(Capacity512, FibonacciSixteenWay);
+    fibonacci_thirty_two_way_512(Capacity512, FibonacciThirtyTwoWay);
 }
 
 criterion_main!(benches);
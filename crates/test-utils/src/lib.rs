@@ -1,7 +1,7 @@
 pub use associative_cache::*;
 
 use quickcheck::{Arbitrary, Gen};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 #[derive(Clone, Debug)]
 pub enum MethodCall {
@@ -96,14 +96,14 @@ impl MethodCalls {
     pub fn run<C, I, R>(self) -> Result<(), String>
     where
         C: Capacity,
-        I: Indices<*mut u64, C>,
+        I: Default + Indices<*mut u64, C>,
         R: Replacement<usize, C> + Default,
     {
         let MethodCalls { calls, entries } = self;
         let mut cache = AssociativeCache::<*mut u64, usize, C, I, R>::default();
         let mut expected = HashMap::<*mut u64, usize>::new();
 
-        for (method, entry) in calls.into_iter().zip(entries.into_iter()) {
+        for (method, entry) in calls.into_iter().zip(entries) {
             if cache.len() != expected.len() {
                 bail!("cache length mismatch");
             }
@@ -165,6 +165,342 @@ impl MethodCalls {
     }
 }
 
+/// An operation on a cache with a small key space, so that traces of these
+/// operations have plenty of hits, re-insertions, and removals.
+#[derive(Clone, Debug)]
+pub enum KeyedMethodCall {
+    Insert(u8),
+    Get(u8),
+    Remove(u8),
+}
+
+impl Arbitrary for KeyedMethodCall {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let key = u8::arbitrary(g) % 16;
+        match g.choose(&[0, 1, 2]).unwrap() {
+            0 => KeyedMethodCall::Insert(key),
+            1 => KeyedMethodCall::Get(key),
+            2 => KeyedMethodCall::Remove(key),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Run the given calls against a cache with `FifoReplacement` and a model that
+/// tracks the insertion order of each set, checking that the oldest entry in
+/// the key's set is always the one that is replaced.
+pub fn check_fifo<C, I>(calls: Vec<KeyedMethodCall>) -> Result<(), String>
+where
+    C: Capacity,
+    I: Default + Indices<*mut u64, C>,
+{
+    let mut cache = AssociativeCache::<*mut u64, usize, C, I, FifoReplacement>::default();
+    let mut expected = HashMap::<*mut u64, usize>::new();
+    // The keys in each set, from oldest to newest insertion, indexed by the
+    // set's first slot.
+    let mut sets = HashMap::<usize, VecDeque<*mut u64>>::new();
+
+    for (val, call) in calls.into_iter().enumerate() {
+        match call {
+            KeyedMethodCall::Insert(key) => {
+                let key = Entry::from((key as usize, val)).key;
+                let mut indices = cache.indices().indices(&key);
+                let ways = indices.len();
+                let set = sets.entry(indices.next().unwrap()).or_default();
+
+                let replaced = if let Some(i) = set.iter().position(|k| *k == key) {
+                    set.remove(i);
+                    Some(key)
+                } else if set.len() == ways {
+                    set.pop_front()
+                } else {
+                    None
+                };
+                set.push_back(key);
+
+                let expected_old = replaced.map(|k| (k, expected.remove(&k).unwrap()));
+                expected.insert(key, val);
+                let actual_old = cache.insert(key, val);
+                if actual_old != expected_old {
+                    bail!(
+                        "expected insert to replace {:?}; replaced {:?}",
+                        expected_old,
+                        actual_old
+                    );
+                }
+            }
+            KeyedMethodCall::Get(key) => {
+                let key = Entry::from((key as usize, val)).key;
+                if cache.get(&key) != expected.get(&key) {
+                    bail!(
+                        "expected get to return {:?}; found {:?}",
+                        expected.get(&key),
+                        cache.get(&key)
+                    );
+                }
+            }
+            KeyedMethodCall::Remove(key) => {
+                let key = Entry::from((key as usize, val)).key;
+                let first = cache.indices().indices(&key).next().unwrap();
+                if let Some(set) = sets.get_mut(&first) {
+                    set.retain(|k| *k != key);
+                }
+                match (cache.remove(&key), expected.remove(&key)) {
+                    (a, b) if a == b => continue,
+                    otherwise => bail!("cache mismatch on remove: {:?}", otherwise),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A cache value that weighs between one and eight units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weighed {
+    pub val: usize,
+    pub weight: usize,
+}
+
+impl Weigh for Weighed {
+    fn weight(&self) -> usize {
+        self.weight
+    }
+}
+
+/// Run the given calls against a `WeightedAssociativeCache` with a budget of
+/// sixteen units, checking that every insertion either fits the new entry
+/// within the budget by evicting entries from its set, or hands it back.
+pub fn check_weighted<C, I, R>(calls: Vec<KeyedMethodCall>) -> Result<(), String>
+where
+    C: Capacity,
+    I: Default + Indices<*mut u64, C>,
+    R: Default + Replacement<Weighed, C>,
+{
+    let budget = 16;
+    let mut cache = WeightedAssociativeCache::<*mut u64, Weighed, C, I, R>::with_budget(budget);
+    let mut expected = HashMap::<*mut u64, Weighed>::new();
+
+    for (val, call) in calls.into_iter().enumerate() {
+        match call {
+            KeyedMethodCall::Insert(key) => {
+                let value = Weighed {
+                    val,
+                    weight: val % 8 + 1,
+                };
+                let key = Entry::from((key as usize, val)).key;
+                let set: Vec<_> = cache.indices().indices(&key).collect();
+                let len = cache.len();
+                let weight = cache.weight();
+                match cache.insert(key, value) {
+                    Ok(evicted) => {
+                        for (k, v) in &evicted {
+                            if expected.remove(k) != Some(*v) {
+                                bail!("evicted {:?} -> {:?}, which was not in the cache", k, v);
+                            }
+                            if !cache.indices().indices(k).eq(set.iter().copied()) {
+                                bail!("evicted {:?}, which is not in {:?}'s set", k, key);
+                            }
+                        }
+                        expected.insert(key, value);
+                    }
+                    Err((k, v)) => {
+                        if k != key || v != value {
+                            bail!("rejected {:?} -> {:?} when inserting {:?}", k, v, key);
+                        }
+                        if cache.len() != len || cache.weight() != weight {
+                            bail!("rejecting {:?} changed the cache", key);
+                        }
+                    }
+                }
+            }
+            KeyedMethodCall::Get(key) => {
+                let key = Entry::from((key as usize, val)).key;
+                if cache.get(&key) != expected.get(&key) {
+                    bail!(
+                        "expected get to return {:?}; found {:?}",
+                        expected.get(&key),
+                        cache.get(&key)
+                    );
+                }
+            }
+            KeyedMethodCall::Remove(key) => {
+                let key = Entry::from((key as usize, val)).key;
+                match (cache.remove(&key), expected.remove(&key)) {
+                    (a, b) if a == b => {}
+                    otherwise => bail!("cache mismatch on remove: {:?}", otherwise),
+                }
+            }
+        }
+
+        let weight: usize = cache.iter().map(|(_, v)| v.weight).sum();
+        if cache.weight() != weight || weight > budget {
+            bail!(
+                "cache weighs {}, but its entries weigh {}",
+                cache.weight(),
+                weight
+            );
+        }
+        if cache.len() != expected.len() {
+            bail!(
+                "cache has {} entries; expected {}",
+                cache.len(),
+                expected.len()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the given calls against an `AdmissionAssociativeCache` with
+/// `TinyLfuAdmission`, checking that only insertions into full sets are ever
+/// rejected, and that rejected insertions leave the cache unchanged.
+pub fn check_admission<C, I, R>(calls: Vec<KeyedMethodCall>) -> Result<(), String>
+where
+    C: Capacity,
+    I: Default + Indices<*mut u64, C>,
+    R: Clone + Default + Replacement<usize, C>,
+{
+    let mut cache =
+        AdmissionAssociativeCache::<*mut u64, usize, C, I, R, TinyLfuAdmission>::default();
+    let mut expected = HashMap::<*mut u64, usize>::new();
+
+    for (val, call) in calls.into_iter().enumerate() {
+        match call {
+            KeyedMethodCall::Insert(key) => {
+                let key = Entry::from((key as usize, val)).key;
+                let set = cache.indices().indices(&key);
+                let ways = set.len();
+                let full = !expected.contains_key(&key)
+                    && expected
+                        .keys()
+                        .filter(|k| cache.indices().indices(k).eq(set.clone()))
+                        .count()
+                        == ways;
+
+                match cache.insert(key, val) {
+                    Ok(old) => {
+                        if let Some((k, v)) = old {
+                            if expected.remove(&k) != Some(v) {
+                                bail!("replaced {:?} -> {}, which was not in the cache", k, v);
+                            }
+                        }
+                        expected.insert(key, val);
+                    }
+                    Err((k, v)) => {
+                        if k != key || v != val {
+                            bail!("rejected {:?} -> {} when inserting {:?}", k, v, key);
+                        }
+                        if !full {
+                            bail!("rejected {:?} without a full set", key);
+                        }
+                    }
+                }
+            }
+            KeyedMethodCall::Get(key) => {
+                let key = Entry::from((key as usize, val)).key;
+                if cache.get(&key) != expected.get(&key) {
+                    bail!(
+                        "expected get to return {:?}; found {:?}",
+                        expected.get(&key),
+                        cache.peek(&key)
+                    );
+                }
+            }
+            KeyedMethodCall::Remove(key) => {
+                let key = Entry::from((key as usize, val)).key;
+                match (cache.remove(&key), expected.remove(&key)) {
+                    (a, b) if a == b => {}
+                    otherwise => bail!("cache mismatch on remove: {:?}", otherwise),
+                }
+            }
+        }
+
+        if cache.len() != expected.len() {
+            bail!(
+                "cache has {} entries; expected {}",
+                cache.len(),
+                expected.len()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the given calls against an `AssociativeCache`, inserting with
+/// `insert_with_relocation`, and check that relocated entries can still be
+/// found and that only entries that were in the cache are ever replaced.
+pub fn check_relocation<C, I, R>(calls: Vec<KeyedMethodCall>) -> Result<(), String>
+where
+    C: Capacity,
+    I: Default + CuckooIndices<*mut u64, C>,
+    R: Default + Replacement<usize, C>,
+{
+    let mut cache = AssociativeCache::<*mut u64, usize, C, I, R>::default();
+    let mut expected = HashMap::<*mut u64, usize>::new();
+
+    for (val, call) in calls.into_iter().enumerate() {
+        match call {
+            KeyedMethodCall::Insert(key) => {
+                let key = Entry::from((key as usize, val)).key;
+                let old = expected.insert(key, val);
+                match cache.insert_with_relocation(key, val) {
+                    Some((k, v)) if k == key => {
+                        if old != Some(v) {
+                            bail!("replaced {:?} -> {}; expected {:?}", k, v, old);
+                        }
+                    }
+                    Some((k, v)) => {
+                        if expected.remove(&k) != Some(v) {
+                            bail!("replaced {:?} -> {}, which was not in the cache", k, v);
+                        }
+                    }
+                    None => {
+                        if old.is_some() {
+                            bail!("did not replace the old entry for {:?}", key);
+                        }
+                    }
+                }
+            }
+            KeyedMethodCall::Get(key) => {
+                let key = Entry::from((key as usize, val)).key;
+                if cache.get(&key) != expected.get(&key) {
+                    bail!(
+                        "expected get to return {:?}; found {:?}",
+                        expected.get(&key),
+                        cache.peek(&key)
+                    );
+                }
+            }
+            KeyedMethodCall::Remove(key) => {
+                let key = Entry::from((key as usize, val)).key;
+                match (cache.remove(&key), expected.remove(&key)) {
+                    (a, b) if a == b => {}
+                    otherwise => bail!("cache mismatch on remove: {:?}", otherwise),
+                }
+            }
+        }
+
+        if cache.len() != expected.len() {
+            bail!(
+                "cache has {} entries; expected {}",
+                cache.len(),
+                expected.len()
+            );
+        }
+        for (key, val) in &expected {
+            if cache.peek(key) != Some(val) {
+                bail!("lost track of {:?} -> {}", key, val);
+            }
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Entry {
     pub key: *mut u64,
@@ -183,6 +519,21 @@ mod quickchecks {
     use super::*;
     use quickcheck::quickcheck;
 
+    /// A capacity that is not a multiple of the associativity of the indices
+    /// that it is used with.
+    struct Capacity6;
+
+    impl Capacity for Capacity6 {
+        const CAPACITY: usize = 6;
+    }
+
+    /// Like `Capacity6`, but with room for two sixteen-way sets.
+    struct Capacity40;
+
+    impl Capacity for Capacity40 {
+        const CAPACITY: usize = 40;
+    }
+
     quickcheck! {
         fn test_pointer_two_way(test: MethodCalls) -> Result<(), String> {
             test.run::<Capacity4, PointerTwoWay, RoundRobinReplacement>()
@@ -199,5 +550,169 @@ mod quickchecks {
         fn test_hash_four_way(test: MethodCalls) -> Result<(), String> {
             test.run::<Capacity8, HashFourWay, RoundRobinReplacement>()
         }
+
+        fn test_hash_two_way_with(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity4, HashTwoWayWith, RoundRobinReplacement>()
+        }
+
+        fn test_hash_four_way_with(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWayWith, RoundRobinReplacement>()
+        }
+
+        fn test_hash_four_way_tree_plru(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWay, TreePlruReplacement>()
+        }
+
+        fn test_hash_four_way_nru(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWay, NruReplacement>()
+        }
+
+        fn test_hash_four_way_clock(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWay, ClockReplacement>()
+        }
+
+        fn test_hash_four_way_srrip(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWay, SrripReplacement>()
+        }
+
+        fn test_hash_four_way_brrip(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWay, BrripReplacement>()
+        }
+
+        fn test_hash_four_way_drrip(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWay, DrripReplacement>()
+        }
+
+        fn test_hash_four_way_lfu(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWay, LfuReplacement>()
+        }
+
+        fn test_hash_four_way_fifo(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWay, FifoReplacement>()
+        }
+
+        fn test_hash_four_way_counter_lru(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWay, CounterLruReplacement>()
+        }
+
+        fn test_hash_four_way_slru(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWay, SlruReplacement>()
+        }
+
+        fn test_hash_four_way_mru(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWay, MruReplacement>()
+        }
+
+        fn test_hash_four_way_wyrand(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, HashFourWay, WyRandReplacement>()
+        }
+
+        fn test_fibonacci_two_way(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, FibonacciTwoWay, RoundRobinReplacement>()
+        }
+
+        fn test_fibonacci_four_way(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, FibonacciFourWay, CounterLruReplacement>()
+        }
+
+        fn test_thin_pointer_two_way(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, ThinPointerTwoWay, RoundRobinReplacement>()
+        }
+
+        fn test_thin_pointer_four_way(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, ThinPointerFourWay, CounterLruReplacement>()
+        }
+
+        fn test_skewed_two_way(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, SkewedTwoWay, CounterLruReplacement>()
+        }
+
+        fn test_skewed_four_way(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, SkewedFourWay, FifoReplacement>()
+        }
+
+        fn test_skewed_four_way_tree_plru(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, SkewedFourWay, TreePlruReplacement>()
+        }
+
+        fn test_skewed_four_way_nru(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, SkewedFourWay, NruReplacement>()
+        }
+
+        fn test_skewed_four_way_clock(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, SkewedFourWay, ClockReplacement>()
+        }
+
+        fn test_skewed_four_way_drrip(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity8, SkewedFourWay, DrripReplacement>()
+        }
+
+        fn test_uneven_capacity_tree_plru(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity6, HashFourWay, TreePlruReplacement>()
+        }
+
+        fn test_uneven_capacity_nru(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity6, HashFourWay, NruReplacement>()
+        }
+
+        fn test_uneven_capacity_clock(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity6, HashFourWay, ClockReplacement>()
+        }
+
+        fn test_uneven_capacity_sixteen_way_clock(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity40, HashSixteenWay, ClockReplacement>()
+        }
+
+        fn test_uneven_capacity_srrip(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity6, HashFourWay, SrripReplacement>()
+        }
+
+        fn test_uneven_capacity_drrip(test: MethodCalls) -> Result<(), String> {
+            test.run::<Capacity6, HashFourWay, DrripReplacement>()
+        }
+
+        fn fifo_order_pointer_two_way(calls: Vec<KeyedMethodCall>) -> Result<(), String> {
+            check_fifo::<Capacity8, PointerTwoWay>(calls)
+        }
+
+        fn fifo_order_hash_four_way(calls: Vec<KeyedMethodCall>) -> Result<(), String> {
+            check_fifo::<Capacity8, HashFourWay>(calls)
+        }
+
+        fn admission_pointer_two_way(calls: Vec<KeyedMethodCall>) -> Result<(), String> {
+            check_admission::<Capacity8, PointerTwoWay, RoundRobinReplacement>(calls)
+        }
+
+        fn admission_hash_four_way_lfu(calls: Vec<KeyedMethodCall>) -> Result<(), String> {
+            check_admission::<Capacity8, HashFourWay, LfuReplacement>(calls)
+        }
+
+        fn relocation_skewed_two_way(calls: Vec<KeyedMethodCall>) -> Result<(), String> {
+            check_relocation::<Capacity8, SkewedTwoWay, CounterLruReplacement>(calls)
+        }
+
+        fn relocation_skewed_four_way_lfu(calls: Vec<KeyedMethodCall>) -> Result<(), String> {
+            check_relocation::<Capacity8, SkewedFourWay, LfuReplacement>(calls)
+        }
+
+        fn weighted_pointer_two_way(calls: Vec<KeyedMethodCall>) -> Result<(), String> {
+            check_weighted::<Capacity8, PointerTwoWay, RoundRobinReplacement>(calls)
+        }
+
+        fn weighted_hash_four_way_tree_plru(calls: Vec<KeyedMethodCall>) -> Result<(), String> {
+            check_weighted::<Capacity8, HashFourWay, TreePlruReplacement>(calls)
+        }
+
+        fn weighted_hash_four_way_clock(calls: Vec<KeyedMethodCall>) -> Result<(), String> {
+            check_weighted::<Capacity8, HashFourWay, ClockReplacement>(calls)
+        }
+
+        fn weighted_hash_four_way_nru(calls: Vec<KeyedMethodCall>) -> Result<(), String> {
+            check_weighted::<Capacity8, HashFourWay, NruReplacement>(calls)
+        }
+
+        fn weighted_hash_four_way_drrip(calls: Vec<KeyedMethodCall>) -> Result<(), String> {
+            check_weighted::<Capacity8, HashFourWay, DrripReplacement>(calls)
+        }
     }
 }
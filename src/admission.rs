@@ -0,0 +1,738 @@
+//! Admission policies, which decide whether a new entry is worth replacing an
+//! existing entry at all, and an associative cache that consults them.
+
+use super::*;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// An admission policy decides whether a new cache entry should replace the
+/// victim that the replacement policy chose for it, or be rejected instead.
+///
+/// Admission is only consulted when inserting into a full set: entries that
+/// fit into an empty slot, or that replace an existing entry for the same key,
+/// are always admitted.
+pub trait Admission<K, C>
+where
+    K: ?Sized,
+    C: Capacity,
+{
+    /// Called whenever `key` is looked up in the cache, whether or not it is
+    /// found.
+    fn record(&mut self, key: &K);
+
+    /// Return `true` if the `candidate` key's new entry should replace the
+    /// `victim` key's entry, or `false` if the new entry should be rejected.
+    fn admit(&mut self, candidate: &K, victim: &K) -> bool;
+}
+
+/// The number of rows in a `TinyLfuAdmission`'s count-min sketch.
+const SKETCH_ROWS: usize = 4;
+
+/// The largest value of a `TinyLfuAdmission`'s 4-bit counters.
+const MAX_COUNT: u8 = 15;
+
+/// By default, the frequency history is aged after this many samples per slot
+/// in the cache.
+const DEFAULT_SAMPLES_PER_SLOT: usize = 10;
+
+/// TinyLFU admission: only replace an entry with a new entry for a key that has
+/// been looked up more frequently.
+///
+/// Key lookup frequencies are estimated with a count-min sketch of 4-bit
+/// counters, which is sized to the cache's capacity. A key's first lookup is
+/// only recorded in a "doorkeeper" bloom filter, and only subsequent lookups
+/// increment its counters, so that keys that are only ever looked up once do
+/// not pollute the sketch.
+///
+/// To keep the history fresh, after every `10 * C::CAPACITY` samples (by
+/// default, or see `TinyLfuAdmission::with_sample_size`) every counter is
+/// halved and the doorkeeper is cleared.
+///
+/// A new entry is admitted only if its key's estimated frequency is strictly
+/// greater than the victim's. This protects a frequently used working set from
+/// being flushed out by scans of keys that are never used again.
+///
+/// Keys are hashed with `H`, which defaults to `DefaultHasher`.
+///
+/// See "TinyLFU: A Highly Efficient Cache Admission Policy" by Einziger et al.
+/// for details.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let cache = AdmissionAssociativeCache::<
+///     String,
+///     usize,
+///     Capacity128,
+///     HashEightWay,
+///     LfuReplacement,
+///     TinyLfuAdmission,
+/// >::default();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TinyLfuAdmission<H = DefaultHasher> {
+    // `SKETCH_ROWS` rows of counters, one after the other.
+    sketch: Vec<u8>,
+    doorkeeper: Vec<u64>,
+    samples: usize,
+    sample_size: Option<usize>,
+    _hasher: PhantomData<H>,
+}
+
+impl<H> TinyLfuAdmission<H> {
+    /// Construct a `TinyLfuAdmission` that ages its frequency history after
+    /// every `samples` lookups.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// let cache = AdmissionAssociativeCache::<
+    ///     String,
+    ///     usize,
+    ///     Capacity128,
+    ///     HashEightWay,
+    ///     LfuReplacement,
+    ///     TinyLfuAdmission,
+    /// >::with_admission_policy(TinyLfuAdmission::with_sample_size(10_000));
+    /// ```
+    #[inline]
+    pub fn with_sample_size(samples: usize) -> Self {
+        assert!(samples > 0);
+        TinyLfuAdmission {
+            sketch: vec![],
+            doorkeeper: vec![],
+            samples: 0,
+            sample_size: Some(samples),
+            _hasher: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn hash<K>(key: &K) -> u64
+    where
+        K: ?Sized + Hash,
+        H: Hasher + Default,
+    {
+        let mut hasher = H::default();
+        key.hash(&mut hasher);
+        // Mix the hash, so that the sketch's positions are independent of the
+        // bits that `HashNWay` uses to choose the key's set.
+        let hash = hasher.finish();
+        (hash ^ (hash >> 32)).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+    }
+
+    /// The `i`th position for `hash` within a table of `len` slots, where `len`
+    /// is a power of two.
+    #[inline]
+    fn position(hash: u64, i: usize, len: usize) -> usize {
+        // Re-mix the hash for each position, so that two keys which collide in
+        // one position are no more likely to collide in any other.
+        let hash = (hash ^ (i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            .wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        (hash ^ (hash >> 31)) as usize & (len - 1)
+    }
+
+    #[inline]
+    fn width(&self) -> usize {
+        self.sketch.len() / SKETCH_ROWS
+    }
+
+    #[inline]
+    fn in_doorkeeper(&self, hash: u64) -> bool {
+        let bits = self.doorkeeper.len() * 64;
+        (0..2).all(|i| {
+            let bit = Self::position(hash, SKETCH_ROWS + i, bits);
+            self.doorkeeper[bit / 64] & (1 << (bit % 64)) != 0
+        })
+    }
+
+    #[inline]
+    fn counters(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let width = self.width();
+        (0..SKETCH_ROWS).map(move |row| row * width + Self::position(hash, row, width))
+    }
+
+    /// Get the estimated lookup frequency of the given key.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// let mut admission: TinyLfuAdmission = TinyLfuAdmission::default();
+    /// for _ in 0..3 {
+    ///     Admission::<str, Capacity64>::record(&mut admission, "hi");
+    /// }
+    /// assert!(admission.estimate("hi") >= 3);
+    /// ```
+    pub fn estimate<K>(&self, key: &K) -> u8
+    where
+        K: ?Sized + Hash,
+        H: Hasher + Default,
+    {
+        if self.sketch.is_empty() {
+            return 0;
+        }
+        let hash = Self::hash(key);
+        let count = self.counters(hash).map(|i| self.sketch[i]).min().unwrap();
+        count + self.in_doorkeeper(hash) as u8
+    }
+}
+
+impl<K, C, H> Admission<K, C> for TinyLfuAdmission<H>
+where
+    K: ?Sized + Hash,
+    C: Capacity,
+    H: Hasher + Default,
+{
+    #[inline]
+    fn record(&mut self, key: &K) {
+        if self.sketch.is_empty() {
+            let width = C::CAPACITY.next_power_of_two().max(16);
+            self.sketch = vec![0; SKETCH_ROWS * width];
+            self.doorkeeper = vec![0; width / 8];
+        }
+
+        let hash = Self::hash(key);
+        if self.in_doorkeeper(hash) {
+            let min = self.counters(hash).map(|i| self.sketch[i]).min().unwrap();
+            if min < MAX_COUNT {
+                // Conservative update: only increment the smallest counters,
+                // since the others already overestimate this key's frequency.
+                for i in self.counters(hash) {
+                    if self.sketch[i] == min {
+                        self.sketch[i] += 1;
+                    }
+                }
+            }
+        } else {
+            let bits = self.doorkeeper.len() * 64;
+            for i in 0..2 {
+                let bit = Self::position(hash, SKETCH_ROWS + i, bits);
+                self.doorkeeper[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+
+        self.samples += 1;
+        let sample_size = self
+            .sample_size
+            .unwrap_or(DEFAULT_SAMPLES_PER_SLOT * C::CAPACITY);
+        if self.samples >= sample_size {
+            self.samples /= 2;
+            for count in &mut self.sketch {
+                *count /= 2;
+            }
+            for bits in &mut self.doorkeeper {
+                *bits = 0;
+            }
+        }
+    }
+
+    #[inline]
+    fn admit(&mut self, candidate: &K, victim: &K) -> bool {
+        self.estimate(candidate) > self.estimate(victim)
+    }
+}
+
+/// A fixed-size associative cache mapping `K` keys to `V` values, which
+/// consults an admission policy before replacing any entry.
+///
+/// Entries are placed and replaced exactly like they are in an
+/// `AssociativeCache` with the same `C`, `I`, and `R` type parameters, except
+/// that when inserting into a full set, the `A` admission policy may reject
+/// the new entry instead of replacing the victim chosen by the replacement
+/// policy. In that case, the new entry is handed back from `insert`.
+///
+/// Every `get` and `get_mut` is recorded by the admission policy, whether or
+/// not the key is found. Inserting is not recorded, since it usually follows a
+/// lookup that missed.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let mut cache = AdmissionAssociativeCache::<
+///     String,
+///     usize,
+///     Capacity1,
+///     HashDirectMapped,
+///     RoundRobinReplacement,
+///     TinyLfuAdmission,
+/// >::default();
+///
+/// // "hot" is looked up a bunch.
+/// for _ in 0..3 {
+///     if cache.get("hot").is_none() {
+///         cache.insert("hot".to_string(), 1).unwrap();
+///     }
+/// }
+///
+/// // So "cold" is not admitted in its place.
+/// assert!(cache.get("cold").is_none());
+/// assert_eq!(
+///     cache.insert("cold".to_string(), 2),
+///     Err(("cold".to_string(), 2)),
+/// );
+/// assert_eq!(cache.get("hot"), Some(&1));
+/// ```
+#[derive(Debug)]
+pub struct AdmissionAssociativeCache<K, V, C, I, R, A>
+where
+    C: Capacity,
+    R: Replacement<V, C>,
+{
+    cache: AssociativeCache<K, V, C, I, R>,
+    admission_policy: A,
+}
+
+impl<K, V, C, I, R, A> Default for AdmissionAssociativeCache<K, V, C, I, R, A>
+where
+    C: Capacity,
+    I: Default,
+    R: Default + Replacement<V, C>,
+    A: Default,
+{
+    fn default() -> Self {
+        AdmissionAssociativeCache::with_admission_policy(A::default())
+    }
+}
+
+impl<K, V, C, I, R, A> AdmissionAssociativeCache<K, V, C, I, R, A>
+where
+    C: Capacity,
+    R: Replacement<V, C>,
+{
+    /// Construct an `AdmissionAssociativeCache` with the given admission
+    /// policy and the default replacement policy.
+    pub fn with_admission_policy(admission_policy: A) -> Self
+    where
+        I: Default,
+        R: Default,
+    {
+        Self::with_policies(R::default(), admission_policy)
+    }
+
+    /// Construct an `AdmissionAssociativeCache` with the given replacement and
+    /// admission policies.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// let cache = AdmissionAssociativeCache::<
+    ///     String,
+    ///     usize,
+    ///     Capacity128,
+    ///     HashEightWay,
+    ///     LfuReplacement,
+    ///     TinyLfuAdmission,
+    /// >::with_policies(
+    ///     LfuReplacement::with_aging_interval(1000),
+    ///     TinyLfuAdmission::with_sample_size(1000),
+    /// );
+    /// ```
+    pub fn with_policies(replacement_policy: R, admission_policy: A) -> Self
+    where
+        I: Default,
+    {
+        Self::with_indices_and_policies(I::default(), replacement_policy, admission_policy)
+    }
+
+    /// Construct an `AdmissionAssociativeCache` with the given `Indices` and
+    /// admission policy, and the default replacement policy.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let cache = AdmissionAssociativeCache::<
+    ///     String,
+    ///     usize,
+    ///     Capacity128,
+    ///     HashEightWayWith,
+    ///     LfuReplacement,
+    ///     TinyLfuAdmission,
+    /// >::with_indices_and_admission_policy(
+    ///     HashEightWayWith::with_hasher(RandomState::new()),
+    ///     TinyLfuAdmission::with_sample_size(1000),
+    /// );
+    /// ```
+    pub fn with_indices_and_admission_policy(indices: I, admission_policy: A) -> Self
+    where
+        R: Default,
+    {
+        Self::with_indices_and_policies(indices, R::default(), admission_policy)
+    }
+
+    /// Construct an `AdmissionAssociativeCache` with the given `Indices`,
+    /// replacement policy, and admission policy.
+    pub fn with_indices_and_policies(
+        indices: I,
+        replacement_policy: R,
+        admission_policy: A,
+    ) -> Self {
+        AdmissionAssociativeCache {
+            cache: AssociativeCache::with_indices_and_replacement_policy(
+                indices,
+                replacement_policy,
+            ),
+            admission_policy,
+        }
+    }
+
+    /// Get a shared reference to this cache's `Indices`.
+    #[inline]
+    pub fn indices(&self) -> &I {
+        self.cache.indices()
+    }
+
+    /// Get a shared reference to this cache's replacement policy.
+    #[inline]
+    pub fn replacement_policy(&self) -> &R {
+        self.cache.replacement_policy()
+    }
+
+    /// Get an exclusive reference to this cache's replacement policy.
+    #[inline]
+    pub fn replacement_policy_mut(&mut self) -> &mut R {
+        self.cache.replacement_policy_mut()
+    }
+
+    /// Get a shared reference to this cache's admission policy.
+    #[inline]
+    pub fn admission_policy(&self) -> &A {
+        &self.admission_policy
+    }
+
+    /// Get an exclusive reference to this cache's admission policy.
+    #[inline]
+    pub fn admission_policy_mut(&mut self) -> &mut A {
+        &mut self.admission_policy
+    }
+
+    /// Get this cache's constant capacity, aka `C::CAPACITY`.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.cache.capacity()
+    }
+
+    /// Get the number of entries in this cache.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Return `true` if there are zero entries in the cache.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Insert a new entry into the cache, unless the admission policy rejects
+    /// it.
+    ///
+    /// If the new entry is inserted, return the old entry for this key or the
+    /// entry that was replaced by this new one, if any. If the admission
+    /// policy rejects the new entry, the cache is unchanged and the new entry
+    /// is returned as an error.
+    ///
+    /// Choosing the victim may change the replacement policy's state, so the
+    /// policy must be `Clone` in order to restore that state when the new
+    /// entry is rejected.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<(K, V)>, (K, V)>
+    where
+        I: Indices<K, C>,
+        K: PartialEq,
+        R: Clone,
+        A: Admission<K, C>,
+    {
+        let set = self.cache.indices.indices(&key);
+        let full = set.clone().all(|index| match &self.cache.entries[index] {
+            Some((k, _)) => *k != key,
+            None => false,
+        });
+        if !full {
+            return Ok(self.cache.insert(key, value));
+        }
+
+        let snapshot = self.cache.replacement_policy.clone();
+        let index = self.cache.choose_for_replacement(set.clone());
+        let victim = &self.cache.entries[index].as_ref().unwrap().0;
+        if self.admission_policy.admit(&key, victim) {
+            Ok(self.cache.replace_entry(index, set, key, value))
+        } else {
+            self.cache.replacement_policy = snapshot;
+            Err((key, value))
+        }
+    }
+
+    /// Get a shared reference to the value for a given key, if it exists in the
+    /// cache.
+    ///
+    /// This is recorded by the admission policy, and counts as a hit for the
+    /// replacement policy if the key is found.
+    #[inline]
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        I: Indices<Q, C>,
+        Q: ?Sized + PartialEq,
+        A: Admission<Q, C>,
+    {
+        self.admission_policy.record(key);
+        self.cache.get(key)
+    }
+
+    /// Get a shared reference to the value for a given key, if it exists in the
+    /// cache, without notifying the replacement or admission policies.
+    #[inline]
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        I: Indices<Q, C>,
+        Q: ?Sized + PartialEq,
+    {
+        self.cache.peek(key)
+    }
+
+    /// Get an exclusive reference to the value for a given key, if it exists in
+    /// the cache.
+    ///
+    /// This is recorded by the admission policy, and counts as a hit for the
+    /// replacement policy if the key is found.
+    #[inline]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        I: Indices<Q, C>,
+        Q: ?Sized + PartialEq,
+        A: Admission<Q, C>,
+    {
+        self.admission_policy.record(key);
+        self.cache.get_mut(key)
+    }
+
+    /// Remove an entry from the cache.
+    ///
+    /// If an entry for the key existed in the cache, it is removed and `Some`
+    /// is returned. Otherwise, `None` is returned.
+    #[inline]
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        I: Indices<Q, C>,
+        Q: ?Sized + PartialEq,
+    {
+        self.cache.remove(key)
+    }
+
+    /// Retain only the cache entries specified by the predicate.
+    ///
+    /// Calls `f` with each entry in the cache, and removes all entries where
+    /// `f` returned false.
+    #[inline]
+    pub fn retain(&mut self, f: impl FnMut(&K, &mut V) -> bool)
+    where
+        I: Indices<K, C>,
+    {
+        self.cache.retain(f)
+    }
+
+    /// Remove all entries from the cache.
+    ///
+    /// The admission policy's history is kept.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.cache.clear()
+    }
+
+    /// Iterate over shared references to this cache's keys and values.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.cache.iter()
+    }
+
+    /// Iterate over shared references to this cache's keys and exclusive
+    /// references to its values.
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        self.cache.iter_mut()
+    }
+
+    /// Consume this cache, and iterate over its keys and values.
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    pub fn into_iter(self) -> IntoIter<K, V> {
+        self.cache.into_iter()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{hits, Fixed};
+    use crate::{Capacity4, Capacity64, ClockReplacement, PointerFourWay};
+    use crate::{RoundRobinReplacement, SrripReplacement};
+
+    fn record(admission: &mut TinyLfuAdmission, key: usize) {
+        Admission::<usize, Capacity64>::record(admission, &key);
+    }
+
+    #[test]
+    fn tiny_lfu_estimate() {
+        let mut admission = TinyLfuAdmission::default();
+        assert_eq!(admission.estimate(&0_usize), 0);
+
+        // The first lookup only goes into the doorkeeper.
+        record(&mut admission, 0);
+        assert_eq!(admission.estimate(&0_usize), 1);
+        assert!(admission.sketch.iter().all(|c| *c == 0));
+
+        for _ in 0..4 {
+            record(&mut admission, 0);
+        }
+        assert_eq!(admission.estimate(&0_usize), 5);
+
+        // Counters saturate.
+        for _ in 0..100 {
+            record(&mut admission, 0);
+        }
+        assert_eq!(admission.estimate(&0_usize), MAX_COUNT + 1);
+
+        assert!(Admission::<usize, Capacity64>::admit(
+            &mut admission,
+            &0,
+            &1
+        ));
+        assert!(!Admission::<usize, Capacity64>::admit(
+            &mut admission,
+            &1,
+            &0
+        ));
+    }
+
+    #[test]
+    fn tiny_lfu_aging() {
+        let mut admission = TinyLfuAdmission::with_sample_size(10);
+        for _ in 0..9 {
+            record(&mut admission, 0);
+        }
+        assert_eq!(admission.estimate(&0_usize), 9);
+
+        // The tenth sample halves the counters and clears the doorkeeper.
+        record(&mut admission, 0);
+        assert_eq!(admission.estimate(&0_usize), 4);
+        assert_eq!(admission.samples, 5);
+    }
+
+    #[test]
+    fn tiny_lfu_resists_scans() {
+        type Cache<A> = AdmissionAssociativeCache<
+            *mut u8,
+            usize,
+            Capacity4,
+            PointerFourWay,
+            RoundRobinReplacement,
+            A,
+        >;
+
+        // Keys 0 through 3 are popular, and every other key is only used once.
+        let trace = || (0..100).flat_map(|i| vec![0, 1, 2, 3, 4 + i]);
+
+        #[derive(Default)]
+        struct AdmitAll;
+
+        impl<K, C: Capacity> Admission<K, C> for AdmitAll {
+            fn record(&mut self, _key: &K) {}
+
+            fn admit(&mut self, _candidate: &K, _victim: &K) -> bool {
+                true
+            }
+        }
+
+        // The one-off keys are never admitted, so the popular keys always hit
+        // after the first iteration.
+        assert_eq!(
+            hits(&mut Cache::<TinyLfuAdmission>::default(), trace()),
+            99 * 4
+        );
+        assert!(hits(&mut Cache::<AdmitAll>::default(), trace()) < 99 * 4);
+    }
+
+    #[test]
+    fn with_indices() {
+        let mut cache =
+            AdmissionAssociativeCache::<
+                *mut u8,
+                usize,
+                Capacity4,
+                Fixed,
+                RoundRobinReplacement,
+                TinyLfuAdmission,
+            >::with_indices_and_admission_policy(Fixed(2), TinyLfuAdmission::default());
+        assert_eq!(cache.indices().0, 2);
+
+        assert_eq!(cache.insert(0 as *mut _, 0), Ok(None));
+        assert_eq!(cache.insert(1 as *mut _, 1), Ok(None));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&(0 as *mut _)), Some(&0));
+        assert_eq!(cache.get(&(1 as *mut _)), Some(&1));
+    }
+
+    #[test]
+    fn rejection_leaves_replacement_policy_unchanged() {
+        // Only admits new entries when it is toggled on.
+        struct Toggle(bool);
+
+        impl<K, C: Capacity> Admission<K, C> for Toggle {
+            fn record(&mut self, _key: &K) {}
+
+            fn admit(&mut self, _candidate: &K, _victim: &K) -> bool {
+                self.0
+            }
+        }
+
+        /// Fill a four-way set, reject `rejections` new entries, and then
+        /// return the entry that an admitted new entry replaces, checking that
+        /// the rejections did not change the replacement policy.
+        fn victim<R>(rejections: usize) -> Option<(*mut u8, usize)>
+        where
+            R: Clone + Default + PartialEq + std::fmt::Debug + Replacement<usize, Capacity4>,
+        {
+            let mut cache = AdmissionAssociativeCache::<
+                *mut u8,
+                usize,
+                Capacity4,
+                PointerFourWay,
+                R,
+                Toggle,
+            >::with_admission_policy(Toggle(false));
+            for key in 0..4 {
+                assert_eq!(cache.insert(key as *mut _, key), Ok(None));
+            }
+            assert_eq!(cache.get(&(1 as *mut _)), Some(&1));
+
+            let policy = cache.replacement_policy().clone();
+            for key in 4..4 + rejections {
+                assert_eq!(cache.insert(key as *mut _, key), Err((key as *mut _, key)));
+            }
+            assert_eq!(cache.replacement_policy(), &policy);
+
+            cache.admission_policy_mut().0 = true;
+            cache.insert(100 as *mut _, 100).unwrap()
+        }
+
+        assert_eq!(victim::<ClockReplacement>(1), victim::<ClockReplacement>(0));
+        assert_eq!(victim::<ClockReplacement>(2), victim::<ClockReplacement>(0));
+        assert_eq!(victim::<SrripReplacement>(1), victim::<SrripReplacement>(0));
+    }
+}
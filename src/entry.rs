@@ -7,8 +7,11 @@ use std::fmt;
 /// A potentially-empty entry in a cache, used to perform get-or-create
 /// operations on the cache.
 ///
-/// Constructed via the `AssociativeCache::entry` method.
-pub struct Entry<'a, K, V, C, I, R>
+/// Constructed via the `AssociativeCache::entry` and
+/// `AssociativeCache::entry_with_hash` methods. The `S` type parameter is the
+/// iterator over the slots of the key's set, which is kept so that inserting
+/// does not need to compute them again.
+pub struct Entry<'a, K, V, C, I, R, S>
 where
     C: Capacity,
     R: Replacement<V, C>,
@@ -16,9 +19,10 @@ where
     pub(crate) cache: &'a mut AssociativeCache<K, V, C, I, R>,
     pub(crate) index: usize,
     pub(crate) kind: EntryKind,
+    pub(crate) set: S,
 }
 
-impl<'a, K, V, C, I, R> fmt::Debug for Entry<'a, K, V, C, I, R>
+impl<'a, K, V, C, I, R, S> fmt::Debug for Entry<'a, K, V, C, I, R, S>
 where
     C: Capacity,
     R: Replacement<V, C>,
@@ -28,6 +32,7 @@ where
             cache: _,
             ref index,
             ref kind,
+            set: _,
         } = self;
         f.debug_struct("Entry")
             .field("index", index)
@@ -47,11 +52,11 @@ pub(crate) enum EntryKind {
     Replace,
 }
 
-impl<'a, K, V, C, I, R> Entry<'a, K, V, C, I, R>
+impl<'a, K, V, C, I, R, S> Entry<'a, K, V, C, I, R, S>
 where
     C: Capacity,
-    I: Indices<K, C>,
     R: Replacement<V, C>,
+    S: ExactSizeIterator<Item = usize> + Clone,
 {
     /// Get the underlying cached data, creating and inserting it into the cache
     /// if it doesn't already exist.
@@ -112,19 +117,27 @@ where
                 Some((_, v)) => v,
                 _ => unreachable!(),
             },
-            EntryKind::Vacant | EntryKind::Replace => {
-                if let EntryKind::Vacant = self.kind {
-                    self.cache.len += 1;
-                }
+            EntryKind::Vacant => {
+                self.cache.len += 1;
                 self.cache.entries[self.index] = Some((make_key(), make_val()));
                 match &mut self.cache.entries[self.index] {
                     Some((_, v)) => {
-                        self.cache.replacement_policy.on_insert(v);
+                        self.cache
+                            .replacement_policy
+                            .on_insert(self.index, self.set, v);
                         v
                     }
                     _ => unreachable!(),
                 }
             }
+            EntryKind::Replace => {
+                self.cache
+                    .replace_entry(self.index, self.set, make_key(), make_val());
+                match &mut self.cache.entries[self.index] {
+                    Some((_, v)) => v,
+                    _ => unreachable!(),
+                }
+            }
         }
     }
 
@@ -160,12 +173,19 @@ where
     /// assert_eq!(*val, 1337);
     /// ```
     #[inline]
-    pub fn take_entry_that_will_be_replaced(&mut self) -> Option<(K, V)> {
+    pub fn take_entry_that_will_be_replaced(&mut self) -> Option<(K, V)>
+    where
+        I: Indices<K, C>,
+    {
         assert!(self.index < C::CAPACITY);
         if let EntryKind::Replace = self.kind {
             self.cache.len -= 1;
             self.kind = EntryKind::Vacant;
-            mem::replace(&mut self.cache.entries[self.index], None)
+            let (k, v) = self.cache.entries[self.index].take().unwrap();
+            self.cache
+                .replacement_policy
+                .on_remove(self.index, self.cache.indices.indices(&k), &v);
+            Some((k, v))
         } else {
             None
         }
@@ -1,20 +1,155 @@
 //! Various kinds of associativity and `Indices` implementations.
 
 use super::{Capacity, Indices};
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::marker::PhantomData;
 use std::ops::Range;
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::sync::Arc;
 
 #[inline]
-fn hash_to_usize<H>(mut hasher: impl Hasher, h: &H) -> usize
+fn hash_to_u64<H>(mut hasher: impl Hasher, h: &H) -> u64
 where
     H: ?Sized + Hash,
 {
     h.hash(&mut hasher);
-    hasher.finish() as usize
+    hasher.finish()
 }
 
+/// `Indices` that find a key's slots from its hash alone, so that a
+/// precomputed hash can be used instead of hashing the key again.
+///
+/// See `AssociativeCache::get_with_hash` and friends, and `HashedKey`.
+pub trait HashIndices<C>
+where
+    C: Capacity,
+{
+    /// The iterator over indices within the range `0..C::CAPACITY` yielding the
+    /// slots in the cache where an entry whose key has the given hash might
+    /// reside.
+    type Indices: ExactSizeIterator<Item = usize> + Clone;
+
+    /// Get the indices within the range `0..C::CAPACITY` representing slots in
+    /// the cache where an entry whose key has the given `hash` might reside.
+    ///
+    /// This must return the same indices as `Indices::indices` does for any
+    /// key with this hash.
+    fn indices_for_hash(&self, hash: u64) -> Self::Indices;
+}
+
+/// A key together with its precomputed hash.
+///
+/// When used with `HashNWay` or skewed-associative indices that use the same
+/// hasher `H`, the stored hash is used to find the key's slots, instead of
+/// hashing the key every time it is looked up. Comparing two `HashedKey`s
+/// compares their hashes before their keys.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let mut cache = AssociativeCache::<
+///     HashedKey<String>,
+///     usize,
+///     Capacity16,
+///     HashTwoWay,
+///     RoundRobinReplacement,
+/// >::default();
+///
+/// // Hash the key once, and then use it for any number of operations.
+/// let key = HashedKey::new("hi".to_string());
+/// cache.insert(key.clone(), 42);
+/// assert_eq!(cache.get(&key), Some(&42));
+/// ```
+pub struct HashedKey<K, H = DefaultHasher> {
+    hash: u64,
+    key: K,
+    _hasher: PhantomData<H>,
+}
+
+impl<K, H> HashedKey<K, H> {
+    /// Construct a `HashedKey`, hashing the key with a default `H` hasher.
+    #[inline]
+    pub fn new(key: K) -> Self
+    where
+        K: Hash,
+        H: Hasher + Default,
+    {
+        let hash = hash_to_u64(H::default(), &key);
+        HashedKey::with_hash(hash, key)
+    }
+
+    /// Construct a `HashedKey` from a key and its already-computed hash.
+    ///
+    /// The `hash` must be the same as what `HashedKey::new` would compute for
+    /// this key, or else it may not be found in caches where it is stored under
+    /// its real hash.
+    #[inline]
+    pub fn with_hash(hash: u64, key: K) -> Self {
+        HashedKey {
+            hash,
+            key,
+            _hasher: PhantomData,
+        }
+    }
+
+    /// Get the key's hash.
+    #[inline]
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Get a shared reference to the key.
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Unwrap the key, discarding its hash.
+    #[inline]
+    pub fn into_key(self) -> K {
+        self.key
+    }
+}
+
+impl<K, H> Clone for HashedKey<K, H>
+where
+    K: Clone,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        HashedKey::with_hash(self.hash, self.key.clone())
+    }
+}
+
+impl<K, H> fmt::Debug for HashedKey<K, H>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HashedKey")
+            .field("hash", &self.hash)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+impl<K, H> PartialEq for HashedKey<K, H>
+where
+    K: PartialEq,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.key == other.key
+    }
+}
+
+impl<K, H> Eq for HashedKey<K, H> where K: Eq {}
+
 macro_rules! define_hash_n_way {
     ( $( $( #[$attr:meta] )* $name:ident => $n:expr; )* ) => { $(
         $( #[ $attr ] )*
@@ -23,6 +158,21 @@ macro_rules! define_hash_n_way {
             _hasher: PhantomData<H>,
         }
 
+        impl<C, H> HashIndices<C> for $name<H>
+        where
+            C: Capacity,
+            H: Hasher + Default,
+        {
+            type Indices = Range<usize>;
+
+            #[inline]
+            fn indices_for_hash(&self, hash: u64) -> Self::Indices {
+                assert!(C::CAPACITY >= $n);
+                let base = hash as usize % (C::CAPACITY / $n) * $n;
+                base..base + $n
+            }
+        }
+
         impl<T, C, H> Indices<T, C> for $name<H>
         where
             T: ?Sized + Hash,
@@ -32,11 +182,22 @@ macro_rules! define_hash_n_way {
             type Indices = Range<usize>;
 
             #[inline]
-            fn indices(key: &T) -> Self::Indices {
-                assert!(C::CAPACITY >= $n);
+            fn indices(&self, key: &T) -> Self::Indices {
                 let hasher = H::default();
-                let base = hash_to_usize(hasher, key) % (C::CAPACITY / $n) * $n;
-                base..base + $n
+                <Self as HashIndices<C>>::indices_for_hash(self, hash_to_u64(hasher, key))
+            }
+        }
+
+        impl<K, C, H> Indices<HashedKey<K, H>, C> for $name<H>
+        where
+            C: Capacity,
+            H: Hasher + Default,
+        {
+            type Indices = Range<usize>;
+
+            #[inline]
+            fn indices(&self, key: &HashedKey<K, H>) -> Self::Indices {
+                <Self as HashIndices<C>>::indices_for_hash(self, key.hash)
             }
         }
     )* }
@@ -74,6 +235,297 @@ define_hash_n_way! {
     HashThirtyTwoWay => 32;
 }
 
+macro_rules! define_hash_n_way_with {
+    ( $( $( #[$attr:meta] )* $name:ident => $n:expr; )* ) => { $(
+        $( #[ $attr ] )*
+        ///
+        /// Unlike the `HashNWay` indices, which build a new `H::default()`
+        /// hasher for every key, this hashes keys with its own `S` instance, so
+        /// the hasher can have state. By default, that is a `RandomState`,
+        /// which is randomly keyed for every cache. This makes it impractical
+        /// for someone who controls the keys to choose ones that all collide
+        /// into the same set, and keep evicting each other.
+        ///
+        /// A randomly keyed hasher places the same keys differently in each
+        /// cache, and in each run of the program. Use `with_hasher` with a
+        /// fixed `BuildHasher` when that is not desired.
+        ///
+        /// See the `Indices` trait's documentation for more on associativity.
+        #[derive(Clone, Debug, Default)]
+        pub struct $name<S = RandomState> {
+            build_hasher: S,
+        }
+
+        impl<S> $name<S> {
+            /// Construct these indices with the given `BuildHasher`.
+            #[inline]
+            pub fn with_hasher(build_hasher: S) -> Self {
+                $name { build_hasher }
+            }
+
+            /// Get a shared reference to the `BuildHasher` that keys are hashed
+            /// with.
+            #[inline]
+            pub fn hasher(&self) -> &S {
+                &self.build_hasher
+            }
+
+            /// Hash a key the same way that these indices do, for use with
+            /// `AssociativeCache::get_with_hash` and friends.
+            #[inline]
+            pub fn hash_key<T>(&self, key: &T) -> u64
+            where
+                T: ?Sized + Hash,
+                S: BuildHasher,
+            {
+                hash_to_u64(self.build_hasher.build_hasher(), key)
+            }
+        }
+
+        impl<C, S> HashIndices<C> for $name<S>
+        where
+            C: Capacity,
+            S: BuildHasher,
+        {
+            type Indices = Range<usize>;
+
+            #[inline]
+            fn indices_for_hash(&self, hash: u64) -> Self::Indices {
+                assert!(C::CAPACITY >= $n);
+                let base = hash as usize % (C::CAPACITY / $n) * $n;
+                base..base + $n
+            }
+        }
+
+        impl<T, C, S> Indices<T, C> for $name<S>
+        where
+            T: ?Sized + Hash,
+            C: Capacity,
+            S: BuildHasher,
+        {
+            type Indices = Range<usize>;
+
+            #[inline]
+            fn indices(&self, key: &T) -> Self::Indices {
+                <Self as HashIndices<C>>::indices_for_hash(self, self.hash_key(key))
+            }
+        }
+    )* }
+}
+
+define_hash_n_way_with! {
+    /// Direct-mapped (i.e. one-way associative) caching based on the key's
+    /// `Hash` implementation and a `BuildHasher`.
+    HashDirectMappedWith => 1;
+    /// Two-way set associative caching based on the key's `Hash`
+    /// implementation and a `BuildHasher`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// // Each cache gets its own randomly keyed hasher.
+    /// let mut cache = AssociativeCache::<
+    ///     String,
+    ///     usize,
+    ///     Capacity64,
+    ///     HashTwoWayWith,
+    ///     RoundRobinReplacement,
+    /// >::default();
+    ///
+    /// cache.insert("hi".to_string(), 42);
+    /// assert_eq!(cache.get("hi"), Some(&42));
+    ///
+    /// // Hash a key once with the cache's hasher, and reuse the hash.
+    /// let hash = cache.indices().hash_key("hi");
+    /// assert_eq!(cache.get_with_hash(hash, "hi"), Some(&42));
+    /// ```
+    HashTwoWayWith => 2;
+    /// Four-way set associative caching based on the key's `Hash`
+    /// implementation and a `BuildHasher`.
+    HashFourWayWith => 4;
+    /// Eight-way set associative caching based on the key's `Hash`
+    /// implementation and a `BuildHasher`.
+    HashEightWayWith => 8;
+    /// Sixteen-way set associative caching based on the key's `Hash`
+    /// implementation and a `BuildHasher`.
+    HashSixteenWayWith => 16;
+    /// 32-way set associative caching based on the key's `Hash` implementation
+    /// and a `BuildHasher`.
+    HashThirtyTwoWayWith => 32;
+}
+
+/// Mix a key's hash into a different, independent-looking hash for each way of
+/// a skewed-associative cache.
+///
+/// This is the SplitMix64 finalizer, applied to the hash offset by a different
+/// constant for each way.
+#[inline]
+fn skew(hash: u64, way: usize) -> u64 {
+    let mut z = hash.wrapping_add((way as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// The indices of the slots that a key maps to in a skewed-associative cache:
+/// one slot in each way's bank.
+///
+/// See `SkewedTwoWay` and `SkewedFourWay`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SkewedIndices {
+    hash: u64,
+    bank_size: usize,
+    way: usize,
+    ways: usize,
+}
+
+impl Iterator for SkewedIndices {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        if self.way == self.ways {
+            return None;
+        }
+        let bank = self.way * self.bank_size;
+        let index = bank + skew(self.hash, self.way) as usize % self.bank_size;
+        self.way += 1;
+        Some(index)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.ways - self.way;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for SkewedIndices {}
+
+/// `Indices` whose sets overlap in different combinations for different keys,
+/// so that entries can be relocated between their own slots to make room for
+/// new entries.
+///
+/// See `AssociativeCache::insert_with_relocation`.
+pub trait CuckooIndices<K, C>: Indices<K, C>
+where
+    K: ?Sized,
+    C: Capacity,
+{
+    /// The maximum number of entries that may be moved to make room for a
+    /// single new entry.
+    const MAX_KICKS: usize;
+}
+
+macro_rules! define_skewed_n_way {
+    ( $( $( #[$attr:meta] )* $name:ident => $n:expr, $kicks:expr; )* ) => { $(
+        $( #[ $attr ] )*
+        ///
+        /// The cache is split into one bank of slots per way, and the key's
+        /// hash is mixed with a different function for each bank to choose one
+        /// slot within it. Two keys that map to the same slot in one bank most
+        /// likely map to different slots in the others, so unlike with the
+        /// `Hash*Way` indices, keys that conflict in one way do not conflict in
+        /// every way.
+        ///
+        /// Because a key's slots are not a contiguous range, and overlap with
+        /// other keys' slots in different combinations, there is no per-set
+        /// metadata to keep. `ClockReplacement`, `NruReplacement`, and
+        /// `TreePlruReplacement` keep per-slot metadata for these sets instead:
+        /// per-slot reference bits with a single shared hand, per-slot
+        /// reference bits, and per-slot LRU respectively. `SetDueling`
+        /// identifies each set by its slot in the first bank when choosing
+        /// leader sets. Policies that only keep per-slot metadata, like
+        /// `CounterLruReplacement` or `FifoReplacement`, work as usual.
+        ///
+        /// These indices also implement `CuckooIndices`, so entries can be
+        /// relocated to make room for new ones with
+        /// `AssociativeCache::insert_with_relocation`.
+        ///
+        /// See "A Case for Two-Way Skewed-Associative Caches" by Seznec for
+        /// details.
+        #[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name<H = DefaultHasher> {
+            _hasher: PhantomData<H>,
+        }
+
+        impl<C, H> HashIndices<C> for $name<H>
+        where
+            C: Capacity,
+            H: Hasher + Default,
+        {
+            type Indices = SkewedIndices;
+
+            #[inline]
+            fn indices_for_hash(&self, hash: u64) -> Self::Indices {
+                assert!(C::CAPACITY >= $n);
+                SkewedIndices {
+                    hash,
+                    bank_size: C::CAPACITY / $n,
+                    way: 0,
+                    ways: $n,
+                }
+            }
+        }
+
+        impl<T, C, H> Indices<T, C> for $name<H>
+        where
+            T: ?Sized + Hash,
+            C: Capacity,
+            H: Hasher + Default,
+        {
+            type Indices = SkewedIndices;
+
+            #[inline]
+            fn indices(&self, key: &T) -> Self::Indices {
+                let hasher = H::default();
+                <Self as HashIndices<C>>::indices_for_hash(self, hash_to_u64(hasher, key))
+            }
+        }
+
+        impl<K, C, H> Indices<HashedKey<K, H>, C> for $name<H>
+        where
+            C: Capacity,
+            H: Hasher + Default,
+        {
+            type Indices = SkewedIndices;
+
+            #[inline]
+            fn indices(&self, key: &HashedKey<K, H>) -> Self::Indices {
+                <Self as HashIndices<C>>::indices_for_hash(self, key.hash)
+            }
+        }
+
+        impl<T, C, H> CuckooIndices<T, C> for $name<H>
+        where
+            T: ?Sized + Hash,
+            C: Capacity,
+            H: Hasher + Default,
+        {
+            const MAX_KICKS: usize = $kicks;
+        }
+
+        impl<K, C, H> CuckooIndices<HashedKey<K, H>, C> for $name<H>
+        where
+            C: Capacity,
+            H: Hasher + Default,
+        {
+            const MAX_KICKS: usize = $kicks;
+        }
+    )* }
+}
+
+define_skewed_n_way! {
+    /// Two-way skewed-associative caching based on the key's `Hash`
+    /// implementation.
+    SkewedTwoWay => 2, 8;
+    /// Four-way skewed-associative caching based on the key's `Hash`
+    /// implementation.
+    SkewedFourWay => 4, 3;
+}
+
 macro_rules! define_pointer_n_way {
     ( $( $( #[$attr:meta] )* $name: ident => $n:expr; )* ) => {
         $(
@@ -88,7 +540,7 @@ macro_rules! define_pointer_n_way {
                 type Indices = Range<usize>;
 
                 #[inline]
-                fn indices(&ptr: &*mut T) -> Self::Indices {
+                fn indices(&self, &ptr: &*mut T) -> Self::Indices {
                     assert!(C::CAPACITY >= $n);
 
                     let ptr = ptr as usize;
@@ -111,8 +563,8 @@ macro_rules! define_pointer_n_way {
                 type Indices = <Self as Indices<*mut T, C>>::Indices;
 
                 #[inline]
-                fn indices(&ptr: &*const T) -> Self::Indices {
-                    <Self as Indices<*mut T, C>>::indices(&(ptr as *mut T))
+                fn indices(&self, &ptr: &*const T) -> Self::Indices {
+                    <Self as Indices<*mut T, C>>::indices(self, &(ptr as *mut T))
                 }
             }
         )*
@@ -147,56 +599,1057 @@ define_pointer_n_way! {
     PointerThirtyTwoWay => 32;
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::Capacity4;
+/// The granularity at which `GranularPointer*` indices distinguish addresses.
+///
+/// Before choosing a set, a pointer's address has the bits in `IGNORE_MASK`
+/// cleared, and is then shifted right by `SHIFT` bits. All addresses within
+/// the same aligned `2^SHIFT`-byte block map to the same set, and any tag bits
+/// that are set in `IGNORE_MASK` do not affect which set an address maps to.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// /// Cache-line granularity for pointers that carry a tag in their top byte.
+/// struct TaggedCacheLine;
+///
+/// impl PointerGranularity for TaggedCacheLine {
+///     const SHIFT: u32 = 6;
+///     const IGNORE_MASK: usize = 0xff << (usize::BITS - 8);
+/// }
+///
+/// let cache = AssociativeCache::<
+///     *mut u8,
+///     usize,
+///     Capacity256,
+///     GranularPointerFourWay<TaggedCacheLine>,
+///     CounterLruReplacement,
+/// >::default();
+/// ```
+pub trait PointerGranularity {
+    /// The number of low address bits to discard.
+    const SHIFT: u32;
 
-    #[test]
-    fn pointer_direct_mapped() {
-        assert_eq!(
-            <PointerDirectMapped as Indices<*mut u64, Capacity4>>::indices(&(0 as *mut u64)),
-            0..1
-        );
-        assert_eq!(
-            <PointerDirectMapped as Indices<*mut u64, Capacity4>>::indices(&(8 as *mut u64)),
-            1..2
-        );
-        assert_eq!(
-            <PointerDirectMapped as Indices<*mut u64, Capacity4>>::indices(&(16 as *mut u64)),
-            2..3
-        );
-        assert_eq!(
-            <PointerDirectMapped as Indices<*mut u64, Capacity4>>::indices(&(24 as *mut u64)),
-            3..4
-        );
-        assert_eq!(
-            <PointerDirectMapped as Indices<*mut u64, Capacity4>>::indices(&(32 as *mut u64)),
-            0..1
-        );
-    }
+    /// The address bits to clear before discarding the low bits.
+    const IGNORE_MASK: usize = 0;
+}
 
-    #[test]
-    fn pointer_two_way() {
-        assert_eq!(
-            <PointerTwoWay as Indices<*mut u64, Capacity4>>::indices(&(0 as *mut u64)),
-            0..2
-        );
-        assert_eq!(
-            <PointerTwoWay as Indices<*mut u64, Capacity4>>::indices(&(8 as *mut u64)),
-            2..4
-        );
-        assert_eq!(
-            <PointerTwoWay as Indices<*mut u64, Capacity4>>::indices(&(16 as *mut u64)),
-            0..2
-        );
-        assert_eq!(
-            <PointerTwoWay as Indices<*mut u64, Capacity4>>::indices(&(24 as *mut u64)),
-            2..4
-        );
-        assert_eq!(
-            <PointerTwoWay as Indices<*mut u64, Capacity4>>::indices(&(32 as *mut u64)),
-            0..2
-        );
+/// 64-byte cache-line granularity for `GranularPointer*` indices.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CacheLineGranularity;
+
+impl PointerGranularity for CacheLineGranularity {
+    const SHIFT: u32 = 6;
+}
+
+/// 4 KiB page granularity for `GranularPointer*` indices.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PageGranularity;
+
+impl PointerGranularity for PageGranularity {
+    const SHIFT: u32 = 12;
+}
+
+macro_rules! define_granular_pointer_n_way {
+    ( $( $( #[$attr:meta] )* $name: ident => $n:expr; )* ) => {
+        $(
+            $( #[$attr] )*
+            ///
+            /// Addresses are distinguished at the granularity given by `G`,
+            /// rather than by the pointee's alignment. See the
+            /// `PointerGranularity` trait for details.
+            ///
+            /// See the `Indices` trait's documentation for more on
+            /// associativity.
+            #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct $name<G> {
+                _granularity: PhantomData<G>,
+            }
+
+            impl<G> Default for $name<G> {
+                #[inline]
+                fn default() -> Self {
+                    $name {
+                        _granularity: PhantomData,
+                    }
+                }
+            }
+
+            impl<T, C, G> Indices<*mut T, C> for $name<G>
+            where
+                C: Capacity,
+                G: PointerGranularity,
+            {
+                type Indices = Range<usize>;
+
+                #[inline]
+                fn indices(&self, &ptr: &*mut T) -> Self::Indices {
+                    assert!(C::CAPACITY >= $n);
+
+                    let addr = ptr as usize & !G::IGNORE_MASK;
+                    let i = addr >> G::SHIFT;
+
+                    let base = i % (C::CAPACITY / $n) * $n;
+                    base..(base + $n)
+                }
+            }
+
+            impl<T, C, G> Indices<*const T, C> for $name<G>
+            where
+                C: Capacity,
+                G: PointerGranularity,
+            {
+                type Indices = <Self as Indices<*mut T, C>>::Indices;
+
+                #[inline]
+                fn indices(&self, &ptr: &*const T) -> Self::Indices {
+                    <Self as Indices<*mut T, C>>::indices(self, &(ptr as *mut T))
+                }
+            }
+        )*
+    };
+}
+
+define_granular_pointer_n_way! {
+    /// Direct-mapped (i.e. one-way associative) caching based on the key's
+    /// pointer value, at a configurable granularity.
+    GranularPointerDirectMapped => 1;
+    /// Two-way set associative caching based on the key's pointer value, at a
+    /// configurable granularity.
+    GranularPointerTwoWay => 2;
+    /// Four-way set associative caching based on the key's pointer value, at a
+    /// configurable granularity.
+    GranularPointerFourWay => 4;
+    /// Eight-way set associative caching based on the key's pointer value, at
+    /// a configurable granularity.
+    GranularPointerEightWay => 8;
+    /// Sixteen-way set associative caching based on the key's pointer value, at
+    /// a configurable granularity.
+    GranularPointerSixteenWay => 16;
+    /// 32-way set associative caching based on the key's pointer value, at a
+    /// configurable granularity.
+    GranularPointerThirtyTwoWay => 32;
+}
+
+/// Use an integer key's bits as they are.
+#[inline]
+fn select_bits(key: u64) -> u64 {
+    key
+}
+
+/// XOR-fold an integer key's high bits into its low bits, so that keys that
+/// only differ in their high bits do not all map to the same set.
+#[inline]
+fn xor_fold(mut key: u64) -> u64 {
+    key ^= key >> 32;
+    key ^= key >> 16;
+    key ^= key >> 8;
+    key
+}
+
+macro_rules! define_integer_n_way {
+    ( $fold:ident; $( $( #[$attr:meta] )* $name:ident => $n:expr; )* ) => {
+        $(
+            $( #[$attr] )*
+            #[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct $name;
+
+            define_integer_n_way!(@impl $fold, $name, $n, u32);
+            define_integer_n_way!(@impl $fold, $name, $n, u64);
+            define_integer_n_way!(@impl $fold, $name, $n, usize);
+        )*
+    };
+
+    ( @impl $fold:ident, $name:ident, $n:expr, $int:ty ) => {
+        impl<C> Indices<$int, C> for $name
+        where
+            C: Capacity
+        {
+            type Indices = Range<usize>;
+
+            #[inline]
+            fn indices(&self, &key: &$int) -> Self::Indices {
+                assert!(C::CAPACITY >= $n);
+
+                // The compiler should be able to turn this modulo into a mask
+                // because of the constant, power-of-two divisor.
+                let sets = (C::CAPACITY / $n) as u64;
+                let base = ($fold(key as u64) % sets) as usize * $n;
+                base..(base + $n)
+            }
+        }
+    };
+}
+
+define_integer_n_way! {
+    select_bits;
+
+    /// Direct-mapped (i.e. one-way associative) caching based on an integer
+    /// key's low bits, without hashing.
+    ///
+    /// See the `Indices` trait's documentation for more on associativity.
+    IntegerDirectMapped => 1;
+    /// Two-way set associative caching based on an integer key's low bits,
+    /// without hashing.
+    ///
+    /// See the `Indices` trait's documentation for more on associativity.
+    IntegerTwoWay => 2;
+    /// Four-way set associative caching based on an integer key's low bits,
+    /// without hashing.
+    ///
+    /// See the `Indices` trait's documentation for more on associativity.
+    IntegerFourWay => 4;
+    /// Eight-way set associative caching based on an integer key's low bits,
+    /// without hashing.
+    ///
+    /// See the `Indices` trait's documentation for more on associativity.
+    IntegerEightWay => 8;
+    /// Sixteen-way set associative caching based on an integer key's low bits,
+    /// without hashing.
+    ///
+    /// See the `Indices` trait's documentation for more on associativity.
+    IntegerSixteenWay => 16;
+    /// 32-way set associative caching based on an integer key's low bits,
+    /// without hashing.
+    ///
+    /// See the `Indices` trait's documentation for more on associativity.
+    IntegerThirtyTwoWay => 32;
+}
+
+define_integer_n_way! {
+    xor_fold;
+
+    /// Direct-mapped (i.e. one-way associative) caching based on an integer
+    /// key, with its high bits XOR-folded into its low bits, without hashing.
+    ///
+    /// See the `Indices` trait's documentation for more on associativity.
+    IntegerFoldedDirectMapped => 1;
+    /// Two-way set associative caching based on an integer key, with its high
+    /// bits XOR-folded into its low bits, without hashing.
+    ///
+    /// See the `Indices` trait's documentation for more on associativity.
+    IntegerFoldedTwoWay => 2;
+    /// Four-way set associative caching based on an integer key, with its high
+    /// bits XOR-folded into its low bits, without hashing.
+    ///
+    /// See the `Indices` trait's documentation for more on associativity.
+    IntegerFoldedFourWay => 4;
+    /// Eight-way set associative caching based on an integer key, with its
+    /// high bits XOR-folded into its low bits, without hashing.
+    ///
+    /// See the `Indices` trait's documentation for more on associativity.
+    IntegerFoldedEightWay => 8;
+    /// Sixteen-way set associative caching based on an integer key, with its
+    /// high bits XOR-folded into its low bits, without hashing.
+    ///
+    /// See the `Indices` trait's documentation for more on associativity.
+    IntegerFoldedSixteenWay => 16;
+    /// 32-way set associative caching based on an integer key, with its high
+    /// bits XOR-folded into its low bits, without hashing.
+    ///
+    /// See the `Indices` trait's documentation for more on associativity.
+    IntegerFoldedThirtyTwoWay => 32;
+}
+
+/// 2^64 divided by the golden ratio, rounded to the nearest odd integer.
+const GOLDEN_RATIO: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Get the set for `key` out of `sets` sets with Fibonacci hashing: the top
+/// `log2(sets)` bits of `key * 2^64 / φ`.
+#[inline]
+fn fibonacci_set(key: u64, sets: usize) -> usize {
+    assert!(
+        sets.is_power_of_two(),
+        "Fibonacci hashing requires a power-of-two number of sets"
+    );
+    let bits = sets.trailing_zeros();
+    // When there is only one set, this would shift by 64, so use
+    // `checked_shr` to get zero instead.
+    key.wrapping_mul(GOLDEN_RATIO)
+        .checked_shr(64 - bits)
+        .unwrap_or(0) as usize
+}
+
+macro_rules! define_fibonacci_n_way {
+    ( $( $( #[$attr:meta] )* $name:ident => $n:expr; )* ) => {
+        $(
+            $( #[$attr] )*
+            ///
+            /// The key is multiplied by `2^64 / φ`, where `φ` is the golden
+            /// ratio, and the top bits of the product select its set. This
+            /// only takes a multiply and a shift, and unlike taking the key's
+            /// low bits directly, every bit of the key affects its set.
+            ///
+            /// Implemented for `u32`, `u64`, and `usize` keys, and for pointer
+            /// keys. Requires a power-of-two capacity, which is true of all
+            /// the provided `Capacity` implementations.
+            ///
+            /// See the `Indices` trait's documentation for more on
+            /// associativity.
+            #[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct $name;
+
+            define_fibonacci_n_way!(@impl $name, $n, u32);
+            define_fibonacci_n_way!(@impl $name, $n, u64);
+            define_fibonacci_n_way!(@impl $name, $n, usize);
+
+            impl<T, C> Indices<*mut T, C> for $name
+            where
+                C: Capacity
+            {
+                type Indices = Range<usize>;
+
+                #[inline]
+                fn indices(&self, &ptr: &*mut T) -> Self::Indices {
+                    <Self as Indices<usize, C>>::indices(self, &(ptr as usize))
+                }
+            }
+
+            impl<T, C> Indices<*const T, C> for $name
+            where
+                C: Capacity
+            {
+                type Indices = Range<usize>;
+
+                #[inline]
+                fn indices(&self, &ptr: &*const T) -> Self::Indices {
+                    <Self as Indices<usize, C>>::indices(self, &(ptr as usize))
+                }
+            }
+        )*
+    };
+
+    ( @impl $name:ident, $n:expr, $int:ty ) => {
+        impl<C> Indices<$int, C> for $name
+        where
+            C: Capacity
+        {
+            type Indices = Range<usize>;
+
+            #[inline]
+            fn indices(&self, &key: &$int) -> Self::Indices {
+                assert!(C::CAPACITY >= $n);
+                let base = fibonacci_set(key as u64, C::CAPACITY / $n) * $n;
+                base..(base + $n)
+            }
+        }
+    };
+}
+
+define_fibonacci_n_way! {
+    /// Direct-mapped (i.e. one-way associative) caching based on Fibonacci
+    /// hashing of the key.
+    FibonacciDirectMapped => 1;
+    /// Two-way set associative caching based on Fibonacci hashing of the key.
+    FibonacciTwoWay => 2;
+    /// Four-way set associative caching based on Fibonacci hashing of the key.
+    FibonacciFourWay => 4;
+    /// Eight-way set associative caching based on Fibonacci hashing of the
+    /// key.
+    FibonacciEightWay => 8;
+    /// Sixteen-way set associative caching based on Fibonacci hashing of the
+    /// key.
+    FibonacciSixteenWay => 16;
+    /// 32-way set associative caching based on Fibonacci hashing of the key.
+    FibonacciThirtyTwoWay => 32;
+}
+
+/// Pointer-like keys that can be identified by the address of the data they
+/// point to.
+///
+/// For pointers to unsized types, such as slices and trait objects, this is
+/// the address part of the fat pointer, without its length or vtable.
+pub trait ThinAddress {
+    /// Get the address of the data that this points to.
+    fn thin_address(&self) -> usize;
+}
+
+impl<T: ?Sized> ThinAddress for *const T {
+    #[inline]
+    fn thin_address(&self) -> usize {
+        self.cast::<u8>() as usize
+    }
+}
+
+impl<T: ?Sized> ThinAddress for *mut T {
+    #[inline]
+    fn thin_address(&self) -> usize {
+        self.cast::<u8>() as usize
+    }
+}
+
+impl<T: ?Sized> ThinAddress for NonNull<T> {
+    #[inline]
+    fn thin_address(&self) -> usize {
+        self.as_ptr().thin_address()
+    }
+}
+
+impl<T: ?Sized> ThinAddress for &T {
+    #[inline]
+    fn thin_address(&self) -> usize {
+        (*self as *const T).thin_address()
+    }
+}
+
+impl<T: ?Sized> ThinAddress for Rc<T> {
+    #[inline]
+    fn thin_address(&self) -> usize {
+        Rc::as_ptr(self).thin_address()
+    }
+}
+
+impl<T: ?Sized> ThinAddress for Arc<T> {
+    #[inline]
+    fn thin_address(&self) -> usize {
+        Arc::as_ptr(self).thin_address()
+    }
+}
+
+/// A key that is compared and hashed by the address that it points to, rather
+/// than by the value that it points to.
+///
+/// References, `Rc<T>`, and `Arc<T>` compare equal whenever their pointees do,
+/// so using them directly as cache keys would let distinct but equal objects
+/// alias each other's entries. Wrapping them in `ByAddress` keys the cache by
+/// object identity instead.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+/// use std::rc::Rc;
+///
+/// let mut cache = AssociativeCache::<
+///     ByAddress<Rc<usize>>,
+///     &'static str,
+///     Capacity64,
+///     ThinPointerFourWay,
+///     CounterLruReplacement,
+/// >::default();
+///
+/// let a = Rc::new(5);
+/// let b = Rc::new(5);
+/// cache.insert(ByAddress(a.clone()), "a");
+///
+/// // `b` is equal to `a`, but it is a different object.
+/// assert_eq!(cache.get(&ByAddress(a)), Some(&"a"));
+/// assert_eq!(cache.get(&ByAddress(b)), None);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ByAddress<P>(pub P);
+
+impl<P> ThinAddress for ByAddress<P>
+where
+    P: ThinAddress,
+{
+    #[inline]
+    fn thin_address(&self) -> usize {
+        self.0.thin_address()
+    }
+}
+
+impl<P> PartialEq for ByAddress<P>
+where
+    P: ThinAddress,
+{
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.thin_address() == other.thin_address()
+    }
+}
+
+impl<P> Eq for ByAddress<P> where P: ThinAddress {}
+
+impl<P> Hash for ByAddress<P>
+where
+    P: ThinAddress,
+{
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.thin_address().hash(state);
+    }
+}
+
+macro_rules! define_thin_pointer_n_way {
+    ( $( $( #[$attr:meta] )* $name:ident => $n:expr; )* ) => {
+        $(
+            $( #[$attr] )*
+            ///
+            /// Works with raw pointers, including pointers to unsized types
+            /// like `*const [u8]` and `*const dyn Trait`, and `NonNull<T>`.
+            /// References, `Rc<T>`, and `Arc<T>` compare by value, so they must
+            /// be wrapped in `ByAddress` to be used as keys, which makes the
+            /// cache keyed by object identity. Since the pointee's alignment is
+            /// not known for unsized types, the address is spread over the sets
+            /// with Fibonacci hashing, rather than by discarding its low bits
+            /// like `PointerNWay` does. This requires a power-of-two capacity,
+            /// which is true of all the provided `Capacity` implementations.
+            ///
+            /// See the `Indices` trait's documentation for more on
+            /// associativity.
+            #[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct $name;
+
+            impl<T, C> Indices<*const T, C> for $name
+            where
+                T: ?Sized,
+                C: Capacity,
+            {
+                type Indices = Range<usize>;
+
+                #[inline]
+                fn indices(&self, key: &*const T) -> Self::Indices {
+                    assert!(C::CAPACITY >= $n);
+                    let address = key.thin_address() as u64;
+                    let base = fibonacci_set(address, C::CAPACITY / $n) * $n;
+                    base..(base + $n)
+                }
+            }
+
+            impl<T, C> Indices<*mut T, C> for $name
+            where
+                T: ?Sized,
+                C: Capacity,
+            {
+                type Indices = Range<usize>;
+
+                #[inline]
+                fn indices(&self, &key: &*mut T) -> Self::Indices {
+                    <Self as Indices<*const T, C>>::indices(self, &(key as *const T))
+                }
+            }
+
+            impl<T, C> Indices<NonNull<T>, C> for $name
+            where
+                T: ?Sized,
+                C: Capacity,
+            {
+                type Indices = Range<usize>;
+
+                #[inline]
+                fn indices(&self, key: &NonNull<T>) -> Self::Indices {
+                    <Self as Indices<*const T, C>>::indices(self, &(key.as_ptr() as *const T))
+                }
+            }
+
+            impl<P, C> Indices<ByAddress<P>, C> for $name
+            where
+                P: ThinAddress,
+                C: Capacity,
+            {
+                type Indices = Range<usize>;
+
+                #[inline]
+                fn indices(&self, key: &ByAddress<P>) -> Self::Indices {
+                    <Self as Indices<*const u8, C>>::indices(
+                        self,
+                        &(key.thin_address() as *const u8),
+                    )
+                }
+            }
+        )*
+    };
+}
+
+define_thin_pointer_n_way! {
+    /// Direct-mapped (i.e. one-way associative) caching based on the address
+    /// that the key points to.
+    ThinPointerDirectMapped => 1;
+    /// Two-way set associative caching based on the address that the key
+    /// points to.
+    ThinPointerTwoWay => 2;
+    /// Four-way set associative caching based on the address that the key
+    /// points to.
+    ThinPointerFourWay => 4;
+    /// Eight-way set associative caching based on the address that the key
+    /// points to.
+    ThinPointerEightWay => 8;
+    /// Sixteen-way set associative caching based on the address that the key
+    /// points to.
+    ThinPointerSixteenWay => 16;
+    /// 32-way set associative caching based on the address that the key points
+    /// to.
+    ThinPointerThirtyTwoWay => 32;
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::test_utils::hits;
+    use crate::{AssociativeCache, Capacity4, Capacity64, CounterLruReplacement};
+    use std::hash::BuildHasherDefault;
+
+    /// A hasher that uses `u64` keys themselves as their hashes, and lets the
+    /// skewing functions do the mixing, because `DefaultHasher`'s output is not
+    /// guaranteed to stay the same.
+    #[derive(Default)]
+    struct IdentityHasher(u64);
+
+    impl Hasher for IdentityHasher {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, _: &[u8]) {
+            unimplemented!()
+        }
+
+        fn write_u64(&mut self, i: u64) {
+            self.0 = i;
+        }
+    }
+
+    #[test]
+    fn pointer_direct_mapped() {
+        assert_eq!(
+            <PointerDirectMapped as Indices<*mut u64, Capacity4>>::indices(
+                &Default::default(),
+                &(0 as *mut u64)
+            ),
+            0..1
+        );
+        assert_eq!(
+            <PointerDirectMapped as Indices<*mut u64, Capacity4>>::indices(
+                &Default::default(),
+                &(8 as *mut u64)
+            ),
+            1..2
+        );
+        assert_eq!(
+            <PointerDirectMapped as Indices<*mut u64, Capacity4>>::indices(
+                &Default::default(),
+                &(16 as *mut u64)
+            ),
+            2..3
+        );
+        assert_eq!(
+            <PointerDirectMapped as Indices<*mut u64, Capacity4>>::indices(
+                &Default::default(),
+                &(24 as *mut u64)
+            ),
+            3..4
+        );
+        assert_eq!(
+            <PointerDirectMapped as Indices<*mut u64, Capacity4>>::indices(
+                &Default::default(),
+                &(32 as *mut u64)
+            ),
+            0..1
+        );
+    }
+
+    #[test]
+    fn pointer_two_way() {
+        assert_eq!(
+            <PointerTwoWay as Indices<*mut u64, Capacity4>>::indices(
+                &Default::default(),
+                &(0 as *mut u64)
+            ),
+            0..2
+        );
+        assert_eq!(
+            <PointerTwoWay as Indices<*mut u64, Capacity4>>::indices(
+                &Default::default(),
+                &(8 as *mut u64)
+            ),
+            2..4
+        );
+        assert_eq!(
+            <PointerTwoWay as Indices<*mut u64, Capacity4>>::indices(
+                &Default::default(),
+                &(16 as *mut u64)
+            ),
+            0..2
+        );
+        assert_eq!(
+            <PointerTwoWay as Indices<*mut u64, Capacity4>>::indices(
+                &Default::default(),
+                &(24 as *mut u64)
+            ),
+            2..4
+        );
+        assert_eq!(
+            <PointerTwoWay as Indices<*mut u64, Capacity4>>::indices(
+                &Default::default(),
+                &(32 as *mut u64)
+            ),
+            0..2
+        );
+    }
+
+    #[test]
+    fn skewed_four_way_has_one_slot_per_bank() {
+        for key in 0..100_u64 {
+            let indices =
+                <SkewedFourWay as Indices<u64, Capacity64>>::indices(&Default::default(), &key);
+            assert_eq!(indices.len(), 4);
+            for (way, index) in indices.enumerate() {
+                assert!((way * 16..(way + 1) * 16).contains(&index));
+            }
+        }
+    }
+
+    #[test]
+    fn skewed_four_way_has_fewer_conflict_misses() {
+        type Cache<I> = AssociativeCache<u64, usize, Capacity64, I, CounterLruReplacement>;
+
+        type HashFourWay = super::HashFourWay<IdentityHasher>;
+        type SkewedFourWay = super::SkewedFourWay<IdentityHasher>;
+
+        // Find eight keys that all map to the same `HashFourWay` set, which is
+        // twice as many as the set can hold.
+        let set = <HashFourWay as Indices<u64, Capacity64>>::indices(&Default::default(), &0);
+        let keys: Vec<usize> = (0..)
+            .filter(|&key| {
+                <HashFourWay as Indices<u64, Capacity64>>::indices(&Default::default(), &key) == set
+            })
+            .take(8)
+            .map(|key| key as usize)
+            .collect();
+        let trace = || (0..10).flat_map(|_| keys.iter().copied());
+
+        // Looping over the keys with LRU always misses with `HashFourWay`, but
+        // the keys are spread out over different slots in each of
+        // `SkewedFourWay`'s banks, so they all stay cached after the first
+        // loop.
+        assert_eq!(hits(&mut Cache::<HashFourWay>::default(), trace()), 0);
+        assert_eq!(hits(&mut Cache::<SkewedFourWay>::default(), trace()), 9 * 8);
+    }
+
+    #[test]
+    fn skewed_two_way_relocation() {
+        fn replaced(relocate: bool) -> usize {
+            let mut cache = AssociativeCache::<
+                u64,
+                u64,
+                Capacity64,
+                SkewedTwoWay<IdentityHasher>,
+                CounterLruReplacement,
+            >::default();
+            let mut replaced = 0;
+            for key in 0..48 {
+                let old = if relocate {
+                    cache.insert_with_relocation(key, key)
+                } else {
+                    cache.insert(key, key)
+                };
+                if old.is_some() {
+                    replaced += 1;
+                }
+            }
+            replaced
+        }
+
+        // Filling the cache to 75% of its capacity replaces entries whenever
+        // both of a key's slots happen to be taken already. Relocating other
+        // entries out of the way avoids most of those replacements.
+        assert_eq!(replaced(false), 6);
+        assert_eq!(replaced(true), 3);
+    }
+
+    #[test]
+    fn integer_two_way() {
+        for (key, set) in [
+            (0_u64, 0..2),
+            (1, 2..4),
+            (2, 0..2),
+            (3, 2..4),
+            (1 << 32, 0..2),
+        ] {
+            assert_eq!(
+                <IntegerTwoWay as Indices<u64, Capacity4>>::indices(&Default::default(), &key),
+                set
+            );
+        }
+        assert_eq!(
+            <IntegerTwoWay as Indices<u32, Capacity4>>::indices(&Default::default(), &3),
+            2..4
+        );
+        assert_eq!(
+            <IntegerTwoWay as Indices<usize, Capacity4>>::indices(&Default::default(), &3),
+            2..4
+        );
+    }
+
+    #[test]
+    fn integer_folded_two_way() {
+        // Unlike with `IntegerTwoWay`, the high bits affect which set a key
+        // maps to.
+        for (key, set) in [
+            (0_u64, 0..2),
+            (1, 2..4),
+            (1 << 8, 2..4),
+            (1 << 32, 2..4),
+            (2 << 32, 0..2),
+        ] {
+            assert_eq!(
+                <IntegerFoldedTwoWay as Indices<u64, Capacity4>>::indices(
+                    &Default::default(),
+                    &key
+                ),
+                set
+            );
+        }
+    }
+
+    #[test]
+    fn fibonacci_two_way() {
+        // The top bit of `key * 2^64 / φ` selects one of the two sets.
+        for (key, set) in [(0_u64, 0..2), (1, 2..4), (2, 0..2), (3, 2..4), (4, 0..2)] {
+            assert_eq!(
+                <FibonacciTwoWay as Indices<u64, Capacity4>>::indices(&Default::default(), &key),
+                set
+            );
+        }
+        assert_eq!(
+            <FibonacciFourWay as Indices<u64, Capacity4>>::indices(&Default::default(), &12345),
+            0..4
+        );
+        assert_eq!(
+            <FibonacciDirectMapped as Indices<*mut u64, Capacity4>>::indices(
+                &Default::default(),
+                &(8 as *mut u64)
+            ),
+            <FibonacciDirectMapped as Indices<usize, Capacity4>>::indices(&Default::default(), &8)
+        );
+    }
+
+    #[test]
+    fn granular_pointer_two_way() {
+        type Lines = GranularPointerTwoWay<CacheLineGranularity>;
+        for (addr, set) in [(0, 0..2), (63, 0..2), (64, 2..4), (127, 2..4), (128, 0..2)] {
+            assert_eq!(
+                <Lines as Indices<*mut u8, Capacity4>>::indices(
+                    &Default::default(),
+                    &(addr as *mut u8)
+                ),
+                set
+            );
+        }
+
+        type Pages = GranularPointerTwoWay<PageGranularity>;
+        for (addr, set) in [(0, 0..2), (4095, 0..2), (4096, 2..4), (8192, 0..2)] {
+            assert_eq!(
+                <Pages as Indices<*const u64, Capacity4>>::indices(
+                    &Default::default(),
+                    &(addr as *const u64)
+                ),
+                set
+            );
+        }
+    }
+
+    #[test]
+    fn granular_pointer_ignore_mask() {
+        struct TopByteTagged;
+
+        impl PointerGranularity for TopByteTagged {
+            const SHIFT: u32 = 3;
+            const IGNORE_MASK: usize = 0xff << (usize::BITS - 8);
+        }
+
+        type Tagged = GranularPointerDirectMapped<TopByteTagged>;
+        let tag = 0x5a_usize << (usize::BITS - 8);
+        for addr in [0, 8, 16, 24] {
+            assert_eq!(
+                <Tagged as Indices<*mut u64, Capacity4>>::indices(
+                    &Default::default(),
+                    &((addr | tag) as *mut u64)
+                ),
+                <Tagged as Indices<*mut u64, Capacity4>>::indices(
+                    &Default::default(),
+                    &(addr as *mut u64)
+                ),
+            );
+        }
+        assert_eq!(
+            <Tagged as Indices<*mut u64, Capacity4>>::indices(
+                &Default::default(),
+                &((8 | tag) as *mut u64)
+            ),
+            1..2
+        );
+    }
+
+    #[test]
+    fn thin_pointer_ignores_metadata() {
+        type Thin = ThinPointerFourWay;
+
+        let bytes = [0_u8; 16];
+        let whole: *const [u8] = &bytes[..];
+        let prefix: *const [u8] = &bytes[..4];
+        assert_eq!(
+            <Thin as Indices<*const [u8], Capacity64>>::indices(&Default::default(), &whole),
+            <Thin as Indices<*const [u8], Capacity64>>::indices(&Default::default(), &prefix)
+        );
+        assert_eq!(
+            <Thin as Indices<*const [u8], Capacity64>>::indices(&Default::default(), &whole),
+            <Thin as Indices<*const u8, Capacity64>>::indices(&Default::default(), &bytes.as_ptr())
+        );
+
+        let value = 42_u64;
+        let debug: *const dyn std::fmt::Debug = &value;
+        let nonnull = NonNull::from(&value);
+        assert_eq!(
+            <Thin as Indices<*const dyn std::fmt::Debug, Capacity64>>::indices(
+                &Default::default(),
+                &debug
+            ),
+            <Thin as Indices<NonNull<u64>, Capacity64>>::indices(&Default::default(), &nonnull)
+        );
+        assert_eq!(
+            <Thin as Indices<ByAddress<&u64>, Capacity64>>::indices(
+                &Default::default(),
+                &ByAddress(&value)
+            ),
+            <Thin as Indices<NonNull<u64>, Capacity64>>::indices(&Default::default(), &nonnull)
+        );
+    }
+
+    #[test]
+    fn thin_pointer_smart_pointer_keys() {
+        let mut cache = AssociativeCache::<
+            ByAddress<Rc<str>>,
+            usize,
+            Capacity64,
+            ThinPointerFourWay,
+            CounterLruReplacement,
+        >::default();
+
+        let hi: Rc<str> = Rc::from("hi");
+        let bye: Rc<str> = Rc::from("bye");
+        assert_eq!(cache.insert(ByAddress(hi.clone()), 1), None);
+        assert_eq!(cache.insert(ByAddress(bye.clone()), 2), None);
+        assert_eq!(cache.get(&ByAddress(hi)), Some(&1));
+        assert_eq!(cache.get(&ByAddress(bye.clone())), Some(&2));
+
+        let shared: Arc<[u8]> = Arc::from(&b"shared"[..]);
+        assert_eq!(
+            <ThinPointerFourWay as Indices<ByAddress<Arc<[u8]>>, Capacity64>>::indices(
+                &Default::default(),
+                &ByAddress(shared.clone())
+            ),
+            <ThinPointerFourWay as Indices<ByAddress<Arc<[u8]>>, Capacity64>>::indices(
+                &Default::default(),
+                &ByAddress(shared)
+            )
+        );
+    }
+
+    #[test]
+    fn by_address_keys_are_identities() {
+        let mut cache = AssociativeCache::<
+            ByAddress<Rc<u64>>,
+            &'static str,
+            Capacity4,
+            ThinPointerFourWay,
+            CounterLruReplacement,
+        >::default();
+
+        let a = Rc::new(5);
+        let b = Rc::new(5);
+        assert_eq!(a, b);
+        assert_ne!(ByAddress(a.clone()), ByAddress(b.clone()));
+        assert_eq!(ByAddress(a.clone()), ByAddress(a.clone()));
+
+        assert_eq!(cache.insert(ByAddress(a.clone()), "a"), None);
+        assert_eq!(cache.get(&ByAddress(b.clone())), None);
+        assert_eq!(cache.insert(ByAddress(b.clone()), "b"), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&ByAddress(a.clone())), Some(&"a"));
+        assert_eq!(cache.get(&ByAddress(b)), Some(&"b"));
+        assert_eq!(cache.remove(&ByAddress(a)), Some("a"));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn hashed_key_uses_stored_hash() {
+        let key = HashedKey::<_>::new("hi");
+        assert_eq!(
+            <HashFourWay as Indices<HashedKey<&str>, Capacity64>>::indices(
+                &Default::default(),
+                &key
+            ),
+            <HashFourWay as Indices<&str, Capacity64>>::indices(&Default::default(), &"hi")
+        );
+        assert_eq!(
+            <SkewedFourWay as Indices<HashedKey<&str>, Capacity64>>::indices(
+                &Default::default(),
+                &key
+            )
+            .collect::<Vec<_>>(),
+            <SkewedFourWay as Indices<&str, Capacity64>>::indices(&Default::default(), &"hi")
+                .collect::<Vec<_>>()
+        );
+
+        // A hasher that can't be used, to check that keys are never rehashed.
+        #[derive(Default)]
+        struct PanicHasher;
+
+        impl Hasher for PanicHasher {
+            fn finish(&self) -> u64 {
+                panic!("should not hash")
+            }
+
+            fn write(&mut self, _: &[u8]) {
+                panic!("should not hash")
+            }
+        }
+
+        let mut cache = AssociativeCache::<
+            HashedKey<String, PanicHasher>,
+            usize,
+            Capacity64,
+            HashFourWay<PanicHasher>,
+            CounterLruReplacement,
+        >::default();
+
+        let hi = HashedKey::with_hash(1, "hi".to_string());
+        let bye = HashedKey::with_hash(2, "bye".to_string());
+        assert_eq!(cache.insert(hi.clone(), 1), None);
+        assert_eq!(cache.insert(bye.clone(), 2), None);
+        assert_eq!(cache.get(&hi), Some(&1));
+        assert_eq!(cache.remove(&bye), Some(2));
+        assert_eq!(cache.get(&bye), None);
+
+        // Keys with the same hash are still compared.
+        assert_eq!(cache.get(&HashedKey::with_hash(1, "bye".to_string())), None);
+    }
+
+    #[test]
+    fn hash_with_resists_colliding_keys() {
+        // Keys that all collide into the first set of a `HashFourWay` cache,
+        // like an attacker could find, since it always hashes the same way.
+        let keys: Vec<u64> = (0..)
+            .filter(|k| {
+                <HashFourWay as Indices<u64, Capacity64>>::indices(&Default::default(), k).start
+                    == 0
+            })
+            .take(64)
+            .collect();
+
+        fn cached<I: Default + Indices<u64, Capacity64>>(keys: &[u64]) -> usize {
+            let mut cache =
+                AssociativeCache::<u64, u64, Capacity64, I, CounterLruReplacement>::default();
+            for &key in keys {
+                cache.insert(key, key);
+            }
+            keys.iter().filter(|&key| cache.get(key).is_some()).count()
+        }
+
+        assert_eq!(cached::<HashFourWay>(&keys), 4);
+        assert!(cached::<HashFourWayWith>(&keys) > 16);
+
+        // With a fixed `BuildHasher`, keys are placed just like `HashFourWay`.
+        let fixed = HashFourWayWith::with_hasher(BuildHasherDefault::<DefaultHasher>::default());
+        for key in &keys {
+            assert_eq!(
+                <HashFourWayWith<_> as Indices<u64, Capacity64>>::indices(&fixed, key),
+                0..4
+            );
+        }
     }
 }
@@ -6,22 +6,28 @@
 
 #![deny(missing_docs, missing_debug_implementations)]
 
+pub mod admission;
 pub mod capacity;
 pub mod entry;
 pub mod indices;
 pub mod iter;
 pub mod replacement;
+pub mod weighted;
 
+#[cfg(test)]
+mod test_utils;
+
+pub use admission::*;
 pub use capacity::*;
 pub use entry::*;
 pub use indices::*;
 pub use iter::*;
 pub use replacement::*;
+pub use weighted::*;
 
 use std::borrow::Borrow;
 use std::cmp::max;
 use std::marker::PhantomData;
-use std::mem;
 
 /// A constant cache capacity.
 ///
@@ -81,21 +87,48 @@ pub trait Capacity {
 ///
 /// ## Provided Implementations
 ///
-/// This crate provides two flavors of associativity out of the box:
+/// This crate provides several families of associativity out of the box, most
+/// of them at every level from direct-mapped to thirty-two-way. See the
+/// `indices` module for the full list.
 ///
 /// 1. `Hash`-based implementations: `HashDirectMapped` and
 ///    `Hash{Two,Four,Eight,Sixteen,ThirtyTwo}Way` provide various associativity
-///    levels based on the key's `Hash` implementation.
+///    levels based on the key's `Hash` implementation. The `Hash*With`
+///    variants use a `BuildHasher` instance, and `HashedKey` lets a key carry
+///    its precomputed hash.
 ///
 /// 2. Pointer-based implementations: `PointerDirectMapped` and
 ///    `Pointer{Two,Four,Eight,Sixteen,ThirtyTwo}Way` provide various
 ///    associativity levels based on the pointer value, taking advantage of its
 ///    referenced type's alignment. This will generally provide faster lookups
-///    than hashing, but is less general.
+///    than hashing, but is less general. `GranularPointer*` map every address
+///    within a cache line or page to the same set, and `ThinPointer*` support
+///    pointers to unsized types and `ByAddress` keys, which key a cache by
+///    object identity.
+///
+/// 3. Integer-based implementations: `Integer*` use an integer key's low bits,
+///    `IntegerFolded*` first XOR-fold its high bits into its low bits, and
+///    `Fibonacci*` use Fibonacci hashing, all without a `Hasher`.
+///
+/// 4. Skewed-associative implementations: `SkewedTwoWay` and `SkewedFourWay`
+///    map a key to a different set in each way's bank, which avoids many
+///    conflict misses. They also implement `CuckooIndices`, so that
+///    `AssociativeCache::insert_with_relocation` can move entries out of the
+///    way instead of replacing them.
+///
+/// ## Stateful Implementations
+///
+/// An `AssociativeCache` owns its `Indices` instance, so implementations may
+/// carry state, such as `HashTwoWayWith`'s randomly keyed `BuildHasher`. Most
+/// implementations are stateless unit structs, and when an implementation
+/// implements `Default`, a cache can be constructed without supplying it. See
+/// `AssociativeCache::with_indices` for caches whose `Indices` need to be
+/// constructed explicitly.
 ///
 /// ## Custom Implementation Requirements
 ///
-/// Implementations must be deterministic.
+/// Implementations must be deterministic: a given instance must always return
+/// the same indices for the same key.
 ///
 /// All indices yielded must be within the capacity.
 ///
@@ -110,11 +143,15 @@ where
 {
     /// The iterator over indices within the range `0..C::CAPACITY` yielding the
     /// slots in the cache where the key's entry might reside.
-    type Indices: ExactSizeIterator<Item = usize>;
+    ///
+    /// This must be `Clone` so that the cache can both search the slots and
+    /// then hand the same set of slots to the `Replacement` policy's hooks
+    /// without computing the indices twice.
+    type Indices: ExactSizeIterator<Item = usize> + Clone;
 
     /// Get the indices within the range `0..C::CAPACITY` representing slots in
     /// the cache where the given key's entry might reside.
-    fn indices(key: &K) -> Self::Indices;
+    fn indices(&self, key: &K) -> Self::Indices;
 }
 
 /// Given that we need to replace a cache entry when inserting a new one, consider
@@ -124,6 +161,52 @@ where
 /// The given iterator will always be non-empty, and its indices will always be
 /// within the capacity, assuming the `Indices` that this is paired with is
 /// conformant.
+///
+/// The candidates always belong to a single set, in the order that `Indices`
+/// yields them, but they are not necessarily all of that set's slots. For
+/// example, a `WeightedAssociativeCache` may need to evict entries from a set
+/// that still has empty slots, in which case only the occupied slots are
+/// candidates.
+///
+/// ## Hooks
+///
+/// Replacement policies are notified of every change to the cache through the
+/// `on_*` hooks, so that they can maintain their own per-slot or per-set
+/// metadata instead of storing it inside the cache values. Each hook is given
+/// the `index` of the affected slot and the `set` of all slots that the
+/// affected entry's key maps to (i.e. the `Indices` for that key, which
+/// includes `index`).
+///
+/// * `on_hit` is called when `get`, `get_mut`, or `entry` finds an existing
+///   entry.
+///
+/// * `on_insert` is called when a new entry is written into an empty slot.
+///
+/// * `on_replace` is called when a new entry is written into a slot that held
+///   a different key's entry, because the old entry was chosen for
+///   replacement.
+///
+/// * `on_update` is called when a key that is already in the cache is inserted
+///   again, overwriting its old value in place.
+///
+/// * `on_remove` is called when an entry is removed via `remove`, `retain`, or
+///   `Entry::take_entry_that_will_be_replaced`.
+///
+/// * `on_clear` is called when every entry is removed via `clear`.
+///
+/// * `on_relocate` is called when `insert_with_relocation` moves an existing
+///   entry from one of its key's slots to another.
+///
+/// Since a new key's entry is usually only inserted after a lookup fails to
+/// find it, `on_insert` and `on_replace` are also how policies observe cache
+/// misses. `on_update` is not a miss.
+///
+/// The `set` iterators are `Clone`, so that policies which combine other
+/// policies can forward them to each of their inner policies.
+///
+/// All hooks have empty default implementations, except for `on_update`,
+/// which defaults to `on_replace`, and `on_relocate`, which defaults to
+/// `on_remove` followed by `on_insert`.
 pub trait Replacement<V, C: Capacity> {
     /// Choose which of the given cache entries will be replaced.
     fn choose_for_replacement<'a>(
@@ -134,13 +217,88 @@ pub trait Replacement<V, C: Capacity> {
         V: 'a;
 
     /// Called whenever an existing cache entry is hit.
-    fn on_hit(&self, value: &V) {
-        let _ = value;
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        let _ = (index, set, value);
     }
 
-    /// Called whenever a new cache entry is inserted.
-    fn on_insert(&self, value: &V) {
-        let _ = value;
+    /// Called whenever a new cache entry is inserted into an empty slot.
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        let _ = (index, set, value);
+    }
+
+    /// Called whenever the `old` value in an occupied slot is overwritten by a
+    /// `new` value.
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        old: &V,
+        new: &V,
+    ) {
+        let _ = (index, set, old, new);
+    }
+
+    /// Called whenever the `old` value of an existing cache entry is
+    /// overwritten by a `new` value, because its key was inserted again.
+    ///
+    /// By default, this is treated like replacing the entry with an entry for
+    /// a different key.
+    #[inline]
+    fn on_update(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        old: &V,
+        new: &V,
+    ) {
+        self.on_replace(index, set, old, new);
+    }
+
+    /// Called whenever a cache entry is removed, leaving its slot empty.
+    #[inline]
+    fn on_remove(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        let _ = (index, set, value);
+    }
+
+    /// Called whenever all cache entries are removed at once.
+    #[inline]
+    fn on_clear(&mut self) {}
+
+    /// Called whenever an existing cache entry is moved from the slot at
+    /// `from` to the empty slot at `to`, both of which are in its `set`.
+    ///
+    /// By default, this is treated as removing the entry and then inserting it
+    /// again, which forgets anything the policy knew about it. Policies that
+    /// keep per-slot metadata should move that metadata along with the entry
+    /// instead.
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        self.on_remove(from, set.clone(), value);
+        self.on_insert(to, set, value);
     }
 }
 
@@ -209,13 +367,14 @@ where
     entries: Vec<Option<(K, V)>>,
     len: usize,
     replacement_policy: R,
+    indices: I,
     _capacity: PhantomData<C>,
-    _indices: PhantomData<I>,
 }
 
 impl<K, V, C, I, R> Default for AssociativeCache<K, V, C, I, R>
 where
     C: Capacity,
+    I: Default,
     R: Default + Replacement<V, C>,
 {
     fn default() -> Self {
@@ -252,7 +411,68 @@ where
     /// >::with_replacement_policy(policy);
     /// # }
     /// ```
-    pub fn with_replacement_policy(replacement_policy: R) -> Self {
+    pub fn with_replacement_policy(replacement_policy: R) -> Self
+    where
+        I: Default,
+    {
+        Self::with_indices_and_replacement_policy(I::default(), replacement_policy)
+    }
+
+    /// Construct an `AssociativeCache` with the given `Indices` and the
+    /// default replacement policy.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// // Share one randomly keyed hasher between two caches.
+    /// let hasher = RandomState::new();
+    ///
+    /// let cache = AssociativeCache::<
+    ///     String,
+    ///     usize,
+    ///     Capacity128,
+    ///     HashEightWayWith,
+    ///     RoundRobinReplacement,
+    /// >::with_indices(HashEightWayWith::with_hasher(hasher.clone()));
+    ///
+    /// let other_cache = AssociativeCache::<
+    ///     String,
+    ///     String,
+    ///     Capacity32,
+    ///     HashEightWayWith,
+    ///     RoundRobinReplacement,
+    /// >::with_indices(HashEightWayWith::with_hasher(hasher));
+    /// ```
+    pub fn with_indices(indices: I) -> Self
+    where
+        R: Default,
+    {
+        Self::with_indices_and_replacement_policy(indices, R::default())
+    }
+
+    /// Construct an `AssociativeCache` with the given `Indices` and
+    /// replacement policy.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// let cache = AssociativeCache::<
+    ///     String,
+    ///     usize,
+    ///     Capacity128,
+    ///     HashEightWayWith,
+    ///     _,
+    /// >::with_indices_and_replacement_policy(
+    ///     HashEightWayWith::default(),
+    ///     LfuReplacement::with_aging_interval(1000),
+    /// );
+    /// ```
+    pub fn with_indices_and_replacement_policy(indices: I, replacement_policy: R) -> Self {
         assert!(C::CAPACITY > 0);
         let mut entries = Vec::with_capacity(C::CAPACITY);
         for _ in 0..C::CAPACITY {
@@ -262,11 +482,20 @@ where
             entries,
             len: 0,
             replacement_policy,
+            indices,
             _capacity: PhantomData,
-            _indices: PhantomData,
         }
     }
 
+    /// Get a shared reference to this cache's `Indices`.
+    ///
+    /// There is no exclusive counterpart, since changing the `Indices` would
+    /// strand the entries that are already in the cache.
+    #[inline]
+    pub fn indices(&self) -> &I {
+        &self.indices
+    }
+
     /// Get a shared reference to this cache's replacement policy.
     #[inline]
     pub fn replacement_policy(&self) -> &R {
@@ -371,6 +600,64 @@ where
     where
         I: Indices<K, C>,
         K: PartialEq,
+    {
+        let set = self.indices.indices(&key);
+        self.insert_in_set(set, key, value)
+    }
+
+    /// Insert a new entry into the cache, using the given precomputed `hash`
+    /// of its key to find its slots.
+    ///
+    /// This is the same as `insert`, except that the key is not hashed again.
+    /// The `hash` must be the hash that the `HashIndices` would compute for
+    /// the key, or else the entry may not be found again, and other entries for
+    /// the same key may remain in the cache.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::{Hash, Hasher};
+    ///
+    /// let mut cache = AssociativeCache::<
+    ///     String,
+    ///     usize,
+    ///     Capacity16,
+    ///     HashTwoWay,
+    ///     RoundRobinReplacement,
+    /// >::default();
+    ///
+    /// let mut hasher = DefaultHasher::default();
+    /// "hi".hash(&mut hasher);
+    /// let hash = hasher.finish();
+    ///
+    /// cache.insert_with_hash(hash, "hi".to_string(), 42);
+    /// assert_eq!(cache.get_with_hash(hash, "hi"), Some(&42));
+    ///
+    /// // The hash is the same one that `HashTwoWay` computes itself.
+    /// assert_eq!(cache.get("hi"), Some(&42));
+    /// ```
+    #[inline]
+    pub fn insert_with_hash(&mut self, hash: u64, key: K, value: V) -> Option<(K, V)>
+    where
+        I: HashIndices<C>,
+        K: PartialEq,
+    {
+        let set = self.indices.indices_for_hash(hash);
+        self.insert_in_set(set, key, value)
+    }
+
+    /// Insert a new entry into the `set` of slots that its key maps to.
+    #[inline]
+    fn insert_in_set(
+        &mut self,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        key: K,
+        value: V,
+    ) -> Option<(K, V)>
+    where
+        K: PartialEq,
     {
         let capacity = self.capacity();
 
@@ -385,7 +672,7 @@ where
         // First see if we can insert the value to an existing entry for this
         // key, or without replaceing any other entry.
         let mut best = None;
-        for index in I::indices(&key) {
+        for index in set.clone() {
             assert!(
                 index < capacity,
                 "`Indices::indices` must always yield indices within the capacity"
@@ -406,21 +693,153 @@ where
             Some(InsertionCandidate::New(index)) => {
                 self.entries[index] = Some((key, value));
                 self.len += 1;
+                let value = &self.entries[index].as_ref().unwrap().1;
+                self.replacement_policy.on_insert(index, set, value);
                 return None;
             }
             Some(InsertionCandidate::Replace(index)) => {
-                return mem::replace(&mut self.entries[index], Some((key, value)));
+                let old = self.entries[index].replace((key, value));
+                if let (Some((_, old)), Some((_, new))) = (&old, &self.entries[index]) {
+                    self.replacement_policy.on_update(index, set, old, new);
+                }
+                return old;
             }
         }
 
         // Okay, we have to replace an entry. Let the `ReplacementPolicy` decide
         // which one.
+        let index = self.choose_for_replacement(set.clone());
+        self.replace_entry(index, set, key, value)
+    }
+
+    /// Insert a new entry into the cache, first trying to make room for it by
+    /// relocating other entries instead of replacing one of them.
+    ///
+    /// This is like `insert`, except when all of the new entry's slots are
+    /// occupied. In that case, it first looks for an occupant that can be moved
+    /// to another one of its own key's slots, which may in turn require moving
+    /// that slot's occupant, and so on, in the style of cuckoo hashing. If a
+    /// chain of at most `I::MAX_KICKS` such moves ends in an empty slot, then
+    /// the entries along it are moved and the new entry takes the freed slot.
+    /// Otherwise, nothing is moved and the replacement policy chooses an entry
+    /// to replace, exactly like `insert`.
+    ///
+    /// Relocation requires `CuckooIndices`, whose sets overlap in different
+    /// combinations for different keys, so that an entry's other slots are not
+    /// all shared with the new entry.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// let mut cache = AssociativeCache::<
+    ///     usize,
+    ///     usize,
+    ///     Capacity64,
+    ///     SkewedTwoWay,
+    ///     CounterLruReplacement,
+    /// >::default();
+    ///
+    /// let mut replaced = 0;
+    /// for i in 0..32 {
+    ///     if cache.insert_with_relocation(i, i).is_some() {
+    ///         replaced += 1;
+    ///     }
+    /// }
+    ///
+    /// // Every entry that was not replaced is still in the cache.
+    /// assert_eq!(cache.len(), 32 - replaced);
+    /// ```
+    pub fn insert_with_relocation(&mut self, key: K, value: V) -> Option<(K, V)>
+    where
+        I: CuckooIndices<K, C>,
+        K: PartialEq,
+    {
+        let set = self.indices.indices(&key);
+        let full = set.clone().all(|index| match &self.entries[index] {
+            Some((k, _)) => *k != key,
+            None => false,
+        });
+        if full {
+            if let Some((mut to, path)) = self.relocation_path(set.clone()) {
+                for (from, from_set) in path {
+                    self.entries[to] = self.entries[from].take();
+                    let (_, v) = self.entries[to].as_ref().unwrap();
+                    self.replacement_policy.on_relocate(from, to, from_set, v);
+                    to = from;
+                }
+            }
+        }
+        self.insert_in_set(set, key, value)
+    }
+
+    /// Find the shortest chain of at most `I::MAX_KICKS` relocations that
+    /// would free up one of the slots in the full `set`.
+    ///
+    /// Returns the empty slot at the end of the chain, and then the occupied
+    /// slots along the chain, each with the set of its entry's key, ending with
+    /// the slot in `set`. Moving each slot's entry into the previous slot, in
+    /// order, frees up the last slot.
+    #[allow(clippy::type_complexity)]
+    fn relocation_path(
+        &self,
+        set: impl Iterator<Item = usize>,
+    ) -> Option<(usize, Vec<(usize, I::Indices)>)>
+    where
+        I: CuckooIndices<K, C>,
+    {
+        // A breadth-first search, where each node is an occupied slot, the
+        // index of its parent node, if any, and the number of moves it takes to
+        // relocate its entry. The set of each node's entry's key is computed
+        // once, when the node is visited.
+        let mut nodes: Vec<(usize, Option<usize>, usize)> =
+            set.map(|index| (index, None, 1)).collect();
+        let mut sets = Vec::with_capacity(nodes.len());
+        let mut next = 0;
+        while next < nodes.len() {
+            let (from, _, kicks) = nodes[next];
+            let (key, _) = self.entries[from].as_ref().unwrap();
+            sets.push(self.indices.indices(key));
+            for to in sets[next].clone() {
+                assert!(
+                    to < C::CAPACITY,
+                    "`Indices::indices` must always yield indices within the capacity"
+                );
+                if nodes.iter().any(|&(index, _, _)| index == to) {
+                    continue;
+                }
+                if self.entries[to].is_none() {
+                    let mut path = vec![];
+                    let mut node = Some(next);
+                    while let Some(n) = node {
+                        path.push((nodes[n].0, sets[n].clone()));
+                        node = nodes[n].1;
+                    }
+                    return Some((to, path));
+                }
+                if kicks < I::MAX_KICKS {
+                    nodes.push((to, Some(next), kicks + 1));
+                }
+            }
+            next += 1;
+        }
+        None
+    }
+
+    /// Let the replacement policy choose which entry in the full `set` to
+    /// replace.
+    fn choose_for_replacement(
+        &mut self,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+    ) -> usize {
+        let capacity = self.capacity();
         let AssociativeCache {
             ref entries,
             ref mut replacement_policy,
             ..
         } = self;
-        let candidates = I::indices(&key).map(|index| {
+        let candidates = set.clone().map(|index| {
             assert!(
                 index < capacity,
                 "`I::indices` must always yield indices within the capacity"
@@ -438,17 +857,36 @@ where
         });
         let index = replacement_policy.choose_for_replacement(candidates);
         debug_assert!(
-            I::indices(&key).any(|i| i == index),
+            set.clone().any(|i| i == index),
             "`ReplacementPolicy::choose_for_replacement` must return a candidate index"
         );
         assert!(index < capacity);
         assert!(self.entries[index].is_some());
-        mem::replace(&mut self.entries[index], Some((key, value)))
+        index
+    }
+
+    /// Overwrite the occupied slot at `index` with the given entry, notify the
+    /// replacement policy, and return the old entry.
+    fn replace_entry(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        key: K,
+        value: V,
+    ) -> Option<(K, V)> {
+        let old = self.entries[index].replace((key, value));
+        if let (Some((_, old)), Some((_, new))) = (&old, &self.entries[index]) {
+            self.replacement_policy.on_replace(index, set, old, new);
+        }
+        old
     }
 
     /// Get a shared reference to the value for a given key, if it exists in the
     /// cache.
     ///
+    /// This counts as a hit for the replacement policy. Use `peek` to look up a
+    /// value without notifying the replacement policy.
+    ///
     /// ## Example
     ///
     /// ```
@@ -471,22 +909,55 @@ where
     /// assert_eq!(cache.get("hi"), Some(&1234));
     /// ```
     #[inline]
-    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
         I: Indices<Q, C>,
         Q: ?Sized + PartialEq,
+    {
+        let set = self.indices.indices(key);
+        self.get_in_set(set, key)
+    }
+
+    /// Get a shared reference to the value for a given key, using the given
+    /// precomputed `hash` of the key to find its slots.
+    ///
+    /// This is the same as `get`, except that the key is not hashed again.
+    /// The `hash` must be the hash that the `HashIndices` would compute for
+    /// the key.
+    #[inline]
+    pub fn get_with_hash<Q>(&mut self, hash: u64, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        I: HashIndices<C>,
+        Q: ?Sized + PartialEq,
+    {
+        let set = self.indices.indices_for_hash(hash);
+        self.get_in_set(set, key)
+    }
+
+    /// Get a shared reference to the value for a given key, looking for it
+    /// in the given `set` of slots.
+    #[inline]
+    fn get_in_set<Q>(
+        &mut self,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        key: &Q,
+    ) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + PartialEq,
     {
         assert_eq!(self.entries.len(), C::CAPACITY);
 
-        for index in I::indices(key) {
+        for index in set.clone() {
             assert!(
                 index < self.entries.len(),
                 "`Indices::indices` must always yield indices within the capacity"
             );
             match &self.entries[index] {
                 Some((k, v)) if k.borrow() == key => {
-                    self.replacement_policy.on_hit(v);
+                    self.replacement_policy.on_hit(index, set, v);
                     return Some(v);
                 }
                 _ => continue,
@@ -496,6 +967,56 @@ where
         None
     }
 
+    /// Get a shared reference to the value for a given key, if it exists in the
+    /// cache, without notifying the replacement policy.
+    ///
+    /// Unlike `get`, this does not count as a hit, and so it will not affect
+    /// which entries are chosen for replacement in the future.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// let mut cache = AssociativeCache::<
+    ///     String,
+    ///     usize,
+    ///     Capacity1,
+    ///     HashDirectMapped,
+    ///     RoundRobinReplacement,
+    /// >::default();
+    ///
+    /// assert!(cache.peek("hi").is_none());
+    ///
+    /// cache.insert("hi".to_string(), 1234);
+    ///
+    /// // Only requires a shared borrow of the cache.
+    /// let cache = &cache;
+    /// assert_eq!(cache.peek("hi"), Some(&1234));
+    /// ```
+    #[inline]
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        I: Indices<Q, C>,
+        Q: ?Sized + PartialEq,
+    {
+        assert_eq!(self.entries.len(), C::CAPACITY);
+
+        for index in self.indices.indices(key) {
+            assert!(
+                index < self.entries.len(),
+                "`Indices::indices` must always yield indices within the capacity"
+            );
+            match &self.entries[index] {
+                Some((k, v)) if k.borrow() == key => return Some(v),
+                _ => continue,
+            }
+        }
+
+        None
+    }
+
     /// Get an exclusive reference to the value for a given key, if it exists in
     /// the cache.
     ///
@@ -530,10 +1051,43 @@ where
         K: Borrow<Q>,
         I: Indices<Q, C>,
         Q: ?Sized + PartialEq,
+    {
+        let set = self.indices.indices(key);
+        self.get_mut_in_set(set, key)
+    }
+
+    /// Get an exclusive reference to the value for a given key, using the
+    /// given precomputed `hash` of the key to find its slots.
+    ///
+    /// This is the same as `get_mut`, except that the key is not hashed again.
+    /// The `hash` must be the hash that the `HashIndices` would compute for
+    /// the key.
+    #[inline]
+    pub fn get_mut_with_hash<Q>(&mut self, hash: u64, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        I: HashIndices<C>,
+        Q: ?Sized + PartialEq,
+    {
+        let set = self.indices.indices_for_hash(hash);
+        self.get_mut_in_set(set, key)
+    }
+
+    /// Get an exclusive reference to the value for a given key, looking for it
+    /// in the given `set` of slots.
+    #[inline]
+    fn get_mut_in_set<Q>(
+        &mut self,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        key: &Q,
+    ) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + PartialEq,
     {
         assert_eq!(self.entries.len(), C::CAPACITY);
 
-        for index in I::indices(key) {
+        for index in set.clone() {
             assert!(
                 index < C::CAPACITY,
                 "`Indices::indices` must always yield indices within the capacity"
@@ -541,7 +1095,7 @@ where
             match &self.entries[index] {
                 Some((k, _)) if k.borrow() == key => {
                     let v = &mut self.entries[index].as_mut().unwrap().1;
-                    self.replacement_policy.on_hit(v);
+                    self.replacement_policy.on_hit(index, set, v);
                     return Some(v);
                 }
                 _ => continue,
@@ -585,10 +1139,42 @@ where
         K: Borrow<Q>,
         I: Indices<Q, C>,
         Q: ?Sized + PartialEq,
+    {
+        let set = self.indices.indices(key);
+        self.remove_in_set(set, key)
+    }
+
+    /// Remove an entry from the cache, using the given precomputed `hash` of
+    /// its key to find its slots.
+    ///
+    /// This is the same as `remove`, except that the key is not hashed again.
+    /// The `hash` must be the hash that the `HashIndices` would compute for
+    /// the key.
+    #[inline]
+    pub fn remove_with_hash<Q>(&mut self, hash: u64, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        I: HashIndices<C>,
+        Q: ?Sized + PartialEq,
+    {
+        let set = self.indices.indices_for_hash(hash);
+        self.remove_in_set(set, key)
+    }
+
+    /// Remove an entry from the given `set` of slots.
+    #[inline]
+    fn remove_in_set<Q>(
+        &mut self,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        key: &Q,
+    ) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + PartialEq,
     {
         assert_eq!(self.entries.len(), C::CAPACITY);
 
-        for index in I::indices(key) {
+        for index in set.clone() {
             assert!(
                 index < self.entries.len(),
                 "`Indices::indices` must always yield indices within the capacity"
@@ -596,7 +1182,9 @@ where
             match &self.entries[index] {
                 Some((k, _)) if k.borrow() == key => {
                     self.len -= 1;
-                    return self.entries[index].take().map(|(_, v)| v);
+                    let (_, v) = self.entries[index].take().unwrap();
+                    self.replacement_policy.on_remove(index, set, &v);
+                    return Some(v);
                 }
                 _ => continue,
             }
@@ -631,17 +1219,52 @@ where
     ///     println!("Last saw character '{}' at index {}", key, val);
     /// }
     /// ```
-    pub fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
-        for e in &mut self.entries {
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool)
+    where
+        I: Indices<K, C>,
+    {
+        for (index, e) in self.entries.iter_mut().enumerate() {
             if let Some((k, v)) = e {
                 if !f(k, v) {
-                    *e = None;
+                    let (k, v) = e.take().unwrap();
                     self.len -= 1;
+                    self.replacement_policy
+                        .on_remove(index, self.indices.indices(&k), &v);
                 }
             }
         }
     }
 
+    /// Remove all entries from the cache.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// let mut cache = AssociativeCache::<
+    ///     String,
+    ///     usize,
+    ///     Capacity4,
+    ///     HashTwoWay,
+    ///     RoundRobinReplacement,
+    /// >::default();
+    ///
+    /// cache.insert("hi".to_string(), 1);
+    /// cache.insert("bye".to_string(), 2);
+    ///
+    /// cache.clear();
+    /// assert!(cache.is_empty());
+    /// assert!(cache.get("hi").is_none());
+    /// ```
+    pub fn clear(&mut self) {
+        for e in &mut self.entries {
+            *e = None;
+        }
+        self.len = 0;
+        self.replacement_policy.on_clear();
+    }
+
     /// Get the key's corresponding slot within the cache for in-place mutation
     /// and performing get-or-create operations.
     ///
@@ -667,18 +1290,52 @@ where
     /// }
     /// ```
     #[inline]
-    pub fn entry<Q>(&mut self, key: &Q) -> Entry<'_, K, V, C, I, R>
+    pub fn entry<Q>(&mut self, key: &Q) -> Entry<'_, K, V, C, I, R, <I as Indices<Q, C>>::Indices>
     where
         K: Borrow<Q>,
         I: Indices<Q, C>,
         Q: ?Sized + PartialEq,
+    {
+        let set = self.indices.indices(key);
+        self.entry_in_set(set, key)
+    }
+
+    /// Get the entry for a given key, using the given precomputed `hash` of
+    /// the key to find its slots.
+    ///
+    /// This is the same as `entry`, except that the key is not hashed again.
+    /// The `hash` must be the hash that the `HashIndices` would compute for
+    /// the key. Inserting through the returned `Entry` reuses the slots found
+    /// from the `hash`, so the owned key is not hashed either.
+    #[inline]
+    pub fn entry_with_hash<Q>(
+        &mut self,
+        hash: u64,
+        key: &Q,
+    ) -> Entry<'_, K, V, C, I, R, <I as HashIndices<C>>::Indices>
+    where
+        K: Borrow<Q>,
+        I: HashIndices<C>,
+        Q: ?Sized + PartialEq,
+    {
+        let set = self.indices.indices_for_hash(hash);
+        self.entry_in_set(set, key)
+    }
+
+    /// Get the entry for a given key in the given `set` of slots.
+    #[inline]
+    fn entry_in_set<Q, S>(&mut self, set: S, key: &Q) -> Entry<'_, K, V, C, I, R, S>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + PartialEq,
+        S: ExactSizeIterator<Item = usize> + Clone,
     {
         let capacity = self.capacity();
 
         // First, see if we have an entry for this key, or if we have an empty
         // slot where an entry could be placed without replaceing another entry.
         let mut empty_index = None;
-        for index in I::indices(key) {
+        for index in set.clone() {
             assert!(
                 index < capacity,
                 "`Indices::indices` must always yield indices within the capacity"
@@ -688,11 +1345,12 @@ where
                     empty_index = Some(index);
                 }
                 Some((k, v)) if (*k).borrow() == key => {
-                    self.replacement_policy.on_hit(v);
+                    self.replacement_policy.on_hit(index, set.clone(), v);
                     return Entry {
                         cache: self,
                         kind: EntryKind::Occupied,
                         index,
+                        set,
                     };
                 }
                 _ => continue,
@@ -703,37 +1361,18 @@ where
                 cache: self,
                 kind: EntryKind::Vacant,
                 index,
+                set,
             };
         }
 
         // Okay, we have to return an already-in-use entry, which will be
         // replaced if the user inserts anything.
-        let AssociativeCache {
-            ref entries,
-            ref mut replacement_policy,
-            ..
-        } = self;
-        let candidates = I::indices(key).map(|index| {
-            assert!(
-                index < capacity,
-                "`I::indices` must always yield indices within the capacity"
-            );
-            let value = &entries[index]
-                .as_ref()
-                // We know that all the indices we saw above are full, so the
-                // only way this `expect` would fail is if `Indices::indices` is
-                // non-deterministic.
-                .expect(
-                    "`Indices::indices` must always yield the same indices for the same entries",
-                )
-                .1;
-            (index, value)
-        });
-        let index = replacement_policy.choose_for_replacement(candidates);
+        let index = self.choose_for_replacement(set.clone());
         Entry {
             cache: self,
             kind: EntryKind::Replace,
             index,
+            set,
         }
     }
 
@@ -835,8 +1474,10 @@ where
 }
 
 #[cfg(test)]
+#[allow(clippy::zero_ptr)]
 mod tests {
     use super::*;
+    use std::mem;
 
     #[test]
     fn replacement_policy() {
@@ -846,6 +1487,348 @@ mod tests {
         assert_eq!(cache.replacement_policy_mut(), &mut policy);
     }
 
+    #[derive(Debug, Default)]
+    struct RecordingReplacement {
+        events: Vec<(&'static str, usize, Vec<usize>)>,
+    }
+
+    impl RecordingReplacement {
+        fn record(&mut self, event: &'static str, index: usize, set: impl Iterator<Item = usize>) {
+            self.events.push((event, index, set.collect()));
+        }
+
+        fn take(&mut self) -> Vec<(&'static str, usize, Vec<usize>)> {
+            mem::take(&mut self.events)
+        }
+    }
+
+    impl<V, C: Capacity> Replacement<V, C> for RecordingReplacement {
+        fn choose_for_replacement<'a>(
+            &mut self,
+            mut candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+        ) -> usize
+        where
+            V: 'a,
+        {
+            candidates.next().unwrap().0
+        }
+
+        fn on_hit(
+            &mut self,
+            index: usize,
+            set: impl ExactSizeIterator<Item = usize> + Clone,
+            _: &V,
+        ) {
+            self.record("hit", index, set);
+        }
+
+        fn on_insert(
+            &mut self,
+            index: usize,
+            set: impl ExactSizeIterator<Item = usize> + Clone,
+            _: &V,
+        ) {
+            self.record("insert", index, set);
+        }
+
+        fn on_replace(
+            &mut self,
+            index: usize,
+            set: impl ExactSizeIterator<Item = usize> + Clone,
+            _: &V,
+            _: &V,
+        ) {
+            self.record("replace", index, set);
+        }
+
+        fn on_update(
+            &mut self,
+            index: usize,
+            set: impl ExactSizeIterator<Item = usize> + Clone,
+            _: &V,
+            _: &V,
+        ) {
+            self.record("update", index, set);
+        }
+
+        fn on_remove(
+            &mut self,
+            index: usize,
+            set: impl ExactSizeIterator<Item = usize> + Clone,
+            _: &V,
+        ) {
+            self.record("remove", index, set);
+        }
+
+        fn on_clear(&mut self) {
+            self.events.push(("clear", 0, vec![]));
+        }
+    }
+
+    #[test]
+    fn with_hash() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut cache = AssociativeCache::<
+            String,
+            usize,
+            Capacity16,
+            HashTwoWay,
+            RecordingReplacement,
+        >::default();
+
+        let hash = |key: &str| {
+            let mut hasher = DefaultHasher::default();
+            key.hash(&mut hasher);
+            hasher.finish()
+        };
+        let set: Vec<_> =
+            <HashTwoWay as Indices<str, Capacity16>>::indices(cache.indices(), "hi").collect();
+
+        assert_eq!(
+            cache.insert_with_hash(hash("hi"), "hi".to_string(), 1),
+            None
+        );
+        assert_eq!(cache.get_with_hash(hash("hi"), "hi"), Some(&1));
+        *cache.get_mut_with_hash(hash("hi"), "hi").unwrap() += 1;
+        assert_eq!(cache.get("hi"), Some(&2));
+        assert_eq!(
+            *cache
+                .entry_with_hash(hash("hi"), "hi")
+                .or_insert_with(|| unreachable!(), || unreachable!()),
+            2
+        );
+        assert_eq!(cache.remove_with_hash(hash("hi"), "hi"), Some(2));
+        assert_eq!(cache.get_with_hash(hash("hi"), "hi"), None);
+        assert_eq!(
+            *cache
+                .entry_with_hash(hash("bye"), "bye")
+                .or_insert_with(|| "bye".to_string(), || 3),
+            3
+        );
+        assert_eq!(cache.get("bye"), Some(&3));
+
+        let events = cache.replacement_policy_mut().take();
+        assert_eq!(events.len(), 8);
+        assert!(events[..6].iter().all(|(_, _, s)| *s == set));
+    }
+
+    #[test]
+    fn sets_are_computed_once() {
+        // Counts how many times a key's slots are computed from the key, and
+        // puts even keys in the first two slots and odd keys in the last two.
+        #[derive(Default)]
+        struct CountingIndices(std::cell::Cell<usize>);
+
+        impl Indices<usize, Capacity4> for CountingIndices {
+            type Indices = std::ops::Range<usize>;
+
+            fn indices(&self, &key: &usize) -> Self::Indices {
+                self.0.set(self.0.get() + 1);
+                self.indices_for_hash(key as u64)
+            }
+        }
+
+        impl HashIndices<Capacity4> for CountingIndices {
+            type Indices = std::ops::Range<usize>;
+
+            fn indices_for_hash(&self, hash: u64) -> Self::Indices {
+                let base = hash as usize % 2 * 2;
+                base..base + 2
+            }
+        }
+
+        impl CuckooIndices<usize, Capacity4> for CountingIndices {
+            const MAX_KICKS: usize = 1;
+        }
+
+        let mut cache = AssociativeCache::<
+            usize,
+            usize,
+            Capacity4,
+            CountingIndices,
+            RoundRobinReplacement,
+        >::default();
+
+        // Inserting through an entry reuses the slots found from the hash.
+        for key in [0, 2, 4] {
+            assert_eq!(
+                *cache
+                    .entry_with_hash(key as u64, &key)
+                    .or_insert_with(|| key, || key),
+                key
+            );
+        }
+        assert_eq!(cache.indices().0.get(), 0);
+
+        // The new key's slots are computed once, and so are those of each
+        // entry that is considered for relocation.
+        assert_eq!(cache.insert_with_relocation(1, 1), None);
+        assert_eq!(cache.indices().0.get(), 1);
+        assert_eq!(cache.insert_with_relocation(3, 3), None);
+        assert_eq!(cache.indices().0.get(), 2);
+        assert_eq!(cache.insert_with_relocation(5, 5), Some((1, 1)));
+        assert_eq!(cache.indices().0.get(), 5);
+    }
+
+    #[test]
+    fn replacement_hooks() {
+        let mut cache = AssociativeCache::<
+            *mut u8,
+            usize,
+            Capacity4,
+            PointerTwoWay,
+            RecordingReplacement,
+        >::default();
+
+        cache.insert(0 as *mut _, 0);
+        cache.insert(2 as *mut _, 2);
+        assert_eq!(
+            cache.replacement_policy_mut().take(),
+            vec![("insert", 1, vec![0, 1]), ("insert", 0, vec![0, 1])]
+        );
+
+        // Re-inserting the same key is an update, while overwriting a
+        // different key is a replacement.
+        cache.insert(0 as *mut _, 10);
+        cache.insert(4 as *mut _, 4);
+        assert_eq!(
+            cache.replacement_policy_mut().take(),
+            vec![("update", 1, vec![0, 1]), ("replace", 0, vec![0, 1])]
+        );
+
+        assert_eq!(cache.get(&(4 as *mut _)), Some(&4));
+        assert_eq!(cache.get_mut(&(0 as *mut _)), Some(&mut 10));
+        assert_eq!(cache.peek(&(4 as *mut _)), Some(&4));
+        assert_eq!(cache.get(&(1 as *mut _)), None);
+        assert_eq!(
+            cache.replacement_policy_mut().take(),
+            vec![("hit", 0, vec![0, 1]), ("hit", 1, vec![0, 1])]
+        );
+
+        assert_eq!(cache.remove(&(4 as *mut _)), Some(4));
+        assert_eq!(cache.remove(&(4 as *mut _)), None);
+        assert_eq!(
+            cache.replacement_policy_mut().take(),
+            vec![("remove", 0, vec![0, 1])]
+        );
+
+        // Vacant, occupied, and replacing entries.
+        cache
+            .entry(&(1 as *mut _))
+            .or_insert_with(|| 1 as *mut _, || 1);
+        cache
+            .entry(&(1 as *mut _))
+            .or_insert_with(|| 1 as *mut _, || 1);
+        cache
+            .entry(&(2 as *mut _))
+            .or_insert_with(|| 2 as *mut _, || 2);
+        cache
+            .entry(&(3 as *mut _))
+            .or_insert_with(|| 3 as *mut _, || 3);
+        assert_eq!(
+            cache.replacement_policy_mut().take(),
+            vec![
+                ("insert", 3, vec![2, 3]),
+                ("hit", 3, vec![2, 3]),
+                ("insert", 0, vec![0, 1]),
+                ("insert", 2, vec![2, 3]),
+            ]
+        );
+
+        let mut entry = cache.entry(&(6 as *mut _));
+        assert_eq!(
+            entry.take_entry_that_will_be_replaced(),
+            Some((2 as *mut _, 2))
+        );
+        entry.or_insert_with(|| 6 as *mut _, || 6);
+        cache
+            .entry(&(5 as *mut _))
+            .or_insert_with(|| 5 as *mut _, || 5);
+        assert_eq!(
+            cache.replacement_policy_mut().take(),
+            vec![
+                ("remove", 0, vec![0, 1]),
+                ("insert", 0, vec![0, 1]),
+                ("replace", 2, vec![2, 3]),
+            ]
+        );
+
+        cache.retain(|_, v| *v != 6);
+        assert_eq!(
+            cache.replacement_policy_mut().take(),
+            vec![("remove", 0, vec![0, 1])]
+        );
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(
+            cache.replacement_policy_mut().take(),
+            vec![("clear", 0, vec![])]
+        );
+    }
+
+    #[test]
+    fn insert_with_relocation() {
+        // Each key maps to the two slots given by its lowest two pairs of
+        // bits.
+        #[derive(Default)]
+        struct PairIndices;
+
+        impl Indices<usize, Capacity4> for PairIndices {
+            type Indices = std::array::IntoIter<usize, 2>;
+
+            fn indices(&self, &key: &usize) -> Self::Indices {
+                IntoIterator::into_iter([key & 3, (key >> 2) & 3])
+            }
+        }
+
+        impl CuckooIndices<usize, Capacity4> for PairIndices {
+            const MAX_KICKS: usize = 4;
+        }
+
+        let mut cache = AssociativeCache::<
+            usize,
+            usize,
+            Capacity4,
+            PairIndices,
+            RecordingReplacement,
+        >::default();
+
+        // Fill every slot except slot 3.
+        assert_eq!(cache.insert_with_relocation(0b00_10, 2), None);
+        assert_eq!(cache.insert_with_relocation(0b00_11, 3), None);
+        assert_eq!(cache.insert_with_relocation(0b11_01, 1), None);
+        assert_eq!(cache.insert_with_relocation(0b10_00, 0), None);
+        assert_eq!(cache.remove(&0b00_11), Some(3));
+        assert_eq!(cache.len(), 3);
+        cache.replacement_policy_mut().take();
+
+        // Both of the new key's slots are full, but the entry in slot 1 can be
+        // moved to slot 3 to make room.
+        assert_eq!(cache.insert_with_relocation(0b01_00, 4), None);
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache.peek(&0b11_01), Some(&1));
+        assert_eq!(cache.peek(&0b01_00), Some(&4));
+        assert_eq!(
+            cache.replacement_policy_mut().take(),
+            vec![
+                ("remove", 1, vec![1, 3]),
+                ("insert", 3, vec![1, 3]),
+                ("insert", 1, vec![0, 1]),
+            ]
+        );
+
+        // With no empty slots left, fall back to the replacement policy.
+        assert_eq!(cache.insert_with_relocation(0b10_01, 5), Some((0b01_00, 4)));
+        assert_eq!(cache.len(), 4);
+        assert_eq!(
+            cache.replacement_policy_mut().take(),
+            vec![("replace", 1, vec![1, 2])]
+        );
+    }
+
     #[test]
     fn capacity() {
         let cache = AssociativeCache::<
@@ -1072,7 +2055,7 @@ mod tests {
         cache.insert(3 as *mut _, 3);
         assert_eq!(cache.len(), 4);
 
-        let mut seen = vec![false; 4];
+        let mut seen = [false; 4];
         for (&k, &v) in &cache {
             assert!(!seen[v]);
             seen[v] = true;
@@ -1097,7 +2080,7 @@ mod tests {
         cache.insert(3 as *mut _, 3);
         assert_eq!(cache.len(), 4);
 
-        let mut seen = vec![false; 4];
+        let mut seen = [false; 4];
         for (&k, v) in &mut cache {
             assert!(!seen[*v]);
             seen[*v] = true;
@@ -1128,7 +2111,7 @@ mod tests {
         cache.insert(3 as *mut _, 3);
         assert_eq!(cache.len(), 4);
 
-        let mut seen = vec![false; 4];
+        let mut seen = [false; 4];
         for (k, v) in cache {
             assert!(!seen[v]);
             seen[v] = true;
@@ -1,11 +1,182 @@
 //! Implementations of various replacement algorithms used when inserting into a
 //! full cache.
+//!
+//! ## Associativity Limits
+//!
+//! `TreePlruReplacement`, `NruReplacement`, `ClockReplacement`, and the RRIP
+//! policies track the ways of a set in 32-bit masks or fixed-size arrays, so
+//! they require that the associativity is no greater than 32, which is true of
+//! all the provided `Indices` implementations. `TreePlruReplacement` also
+//! requires that the associativity is a power of two.
+//!
+//! ## Skewed Sets
+//!
+//! Policies that keep metadata for each set, such as a tree, a clock hand, or
+//! reference bits, find it by numbering sets that are aligned ranges of
+//! contiguous slots. The sets of `SkewedTwoWay` and `SkewedFourWay` are not:
+//! every slot belongs to many different sets. For those sets, the policies keep
+//! their metadata per slot instead:
+//!
+//! * `TreePlruReplacement` records when each slot was last used, and the least
+//!   recently used candidate is the victim, exactly like
+//!   `CounterLruReplacement`.
+//!
+//! * `NruReplacement` keeps a reference bit per slot, and the bits are cleared
+//!   whenever every slot in the referenced entry's set would be referenced.
+//!
+//! * `ClockReplacement` keeps a reference bit per slot, and a single hand
+//!   position is shared by all sets: the sweep starts at that position among
+//!   the candidates, in the order that `Indices` yields them, and only ever
+//!   visits candidates.
 
 pub use super::{Capacity, Replacement};
 
+pub mod clock;
+pub use clock::*;
+
+pub mod dueling;
+pub use dueling::*;
+
+pub mod fifo;
+pub use fifo::*;
+
+pub mod greedy_dual;
+pub use greedy_dual::*;
+
+pub mod lfu;
+pub use lfu::*;
+
 pub mod lru;
 pub use lru::*;
 
+pub mod mru;
+pub use mru::*;
+
+pub mod nru;
+pub use nru::*;
+
+pub mod optimal;
+pub use optimal::*;
+
+pub mod plru;
+pub use plru::*;
+
+pub mod prefer;
+pub use prefer::*;
+
+pub mod rrip;
+pub use rrip::*;
+
+pub mod slru;
+pub use slru::*;
+
+pub mod wyrand;
+pub use wyrand::*;
+
+/// Locate the slot at `index` within its `set`, returning `(set_number, way,
+/// ways)`.
+///
+/// Sets are numbered by their first slot's index divided by their
+/// associativity. This numbering is dense and unique for sets that are aligned
+/// ranges of contiguous slots (see `is_aligned_range`), and is what policies
+/// that keep per-set metadata use to find it.
+#[inline]
+pub(crate) fn locate(
+    index: usize,
+    set: impl ExactSizeIterator<Item = usize>,
+) -> (usize, usize, usize) {
+    let ways = set.len();
+    let mut first = None;
+    let mut way = None;
+    for (w, i) in set.enumerate() {
+        first = first.or(Some(i));
+        if i == index {
+            way = Some(w);
+            break;
+        }
+    }
+    let first = first.expect("`Indices::indices` must always be non-empty");
+    let way = way.expect("a slot's set must contain the slot's index");
+    (first / ways, way, ways)
+}
+
+/// Return `true` if the `set` is an aligned range of contiguous slots, which
+/// `locate` can number.
+///
+/// This is true of the sets of all the provided `Indices` implementations
+/// except the skewed-associative ones. Policies that keep per-set metadata keep
+/// per-slot metadata instead for sets that are not aligned ranges. A cache's
+/// sets are assumed to either all be aligned ranges or all not be, so when
+/// choosing an entry to replace, those policies can tell which kind of set the
+/// candidates belong to by which kind of metadata they have allocated.
+#[inline]
+pub(crate) fn is_aligned_range(set: impl ExactSizeIterator<Item = usize>) -> bool {
+    let ways = set.len();
+    let mut set = set.enumerate();
+    match set.next() {
+        Some((_, first)) if first % ways == 0 => set.all(|(way, index)| index == first + way),
+        _ => false,
+    }
+}
+
+/// Get the associativity of the cache's sets, given the associativity `ways`
+/// that a policy recorded from its hooks (or zero if it has not seen any set
+/// yet), or else assume that the candidates make up a whole set.
+///
+/// Policies that keep per-set metadata use this when choosing an entry to
+/// replace, since the candidates are not necessarily all of a set's slots, and
+/// the capacity is not necessarily a multiple of the associativity. With the
+/// same assumptions as `locate`, slot `index` is then way `index % ways` of set
+/// `index / ways`.
+#[inline]
+pub(crate) fn set_ways(ways: usize, candidates: usize) -> usize {
+    if ways == 0 {
+        candidates
+    } else {
+        ways
+    }
+}
+
+/// Get the per-slot metadata for the slot at `index`, lazily allocating
+/// metadata for all of the cache's slots on first use.
+#[inline]
+pub(crate) fn per_slot<T, C>(metadata: &mut Vec<T>, index: usize) -> &mut T
+where
+    T: Clone + Default,
+    C: Capacity,
+{
+    if metadata.is_empty() {
+        metadata.resize(C::CAPACITY, T::default());
+    }
+    &mut metadata[index]
+}
+
+/// Move the per-slot metadata for the slot at `from` to the slot at `to`, and
+/// reset `from`'s metadata, when an entry is relocated.
+#[inline]
+pub(crate) fn relocate_per_slot<T, C>(metadata: &mut Vec<T>, from: usize, to: usize)
+where
+    T: Clone + Default,
+    C: Capacity,
+{
+    let moved = std::mem::take(per_slot::<_, C>(metadata, from));
+    *per_slot::<_, C>(metadata, to) = moved;
+}
+
+/// Get the per-set metadata for `set_number`, lazily allocating metadata for
+/// all of the cache's sets on first use.
+#[inline]
+pub(crate) fn per_set<T, C>(metadata: &mut Vec<T>, set_number: usize, ways: usize) -> &mut T
+where
+    T: Clone + Default,
+    C: Capacity,
+{
+    if metadata.is_empty() {
+        metadata.resize(C::CAPACITY / ways, T::default());
+    }
+    &mut metadata[set_number]
+}
+
 /// Choose cache entries to replace in a round-robin order.
 ///
 /// When considering `n` items to potentially replace, first it will replace the
@@ -43,7 +214,8 @@ where
 ///
 /// When considering `n` items to potentially replace, choose one at random.
 ///
-/// **Requires the `"rand"` feature to be enabled.**
+/// **Requires the `"rand"` feature to be enabled.** See `WyRandReplacement`
+/// for a random policy that is always available.
 #[cfg(feature = "rand")]
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct RandomReplacement<R = rand::rngs::StdRng> {
@@ -0,0 +1,293 @@
+//! CLOCK (second chance) replacement policy implementation.
+
+use super::*;
+
+/// CLOCK cache replacement, also known as second chance replacement.
+///
+/// Each set has a clock hand pointing at one of its ways, and each slot has a
+/// reference bit that is set whenever its entry is hit. When choosing an entry
+/// to replace, the hand sweeps around the set: referenced entries have their
+/// bit cleared and are given a second chance, and the first unreferenced entry
+/// is the victim. The hand is then left pointing just past the victim, so the
+/// newly inserted entry is the last one the hand will reach.
+///
+/// Newly inserted entries start out unreferenced, so an entry must be hit at
+/// least once to survive a sweep.
+///
+/// Unlike `RoundRobinReplacement`, which shares a single counter between all
+/// sets, every set has its own hand, so replacement within one set does not
+/// depend on what happens in other sets. This gives close to LRU quality at
+/// close to round-robin cost.
+///
+/// See the [`replacement`](crate::replacement) module documentation for its
+/// associativity limit and how it handles skewed sets.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let cache = AssociativeCache::<
+///     String,
+///     usize,
+///     Capacity128,
+///     HashEightWay,
+///     ClockReplacement,
+/// >::default();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClockReplacement {
+    sets: Vec<ClockSet>,
+    // The associativity of the aligned sets seen so far, or zero if none have
+    // been.
+    ways: usize,
+    // The hand position shared by all sets that are not aligned ranges.
+    hand: usize,
+    // The reference bit for each slot, for sets that are not aligned ranges.
+    referenced: Vec<bool>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct ClockSet {
+    // The way that the clock hand is pointing at.
+    hand: usize,
+    // The reference bits, where bit `i` is the reference bit for way `i`.
+    referenced: u32,
+}
+
+impl ClockReplacement {
+    #[inline]
+    fn set<C: Capacity>(&mut self, set_number: usize, ways: usize) -> &mut ClockSet {
+        assert!(
+            ways <= 32,
+            "`ClockReplacement` requires an associativity no greater than 32"
+        );
+        per_set::<_, C>(&mut self.sets, set_number, ways)
+    }
+
+    #[inline]
+    fn set_referenced<C: Capacity>(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        referenced: bool,
+    ) {
+        if !is_aligned_range(set.clone()) {
+            *per_slot::<_, C>(&mut self.referenced, index) = referenced;
+            return;
+        }
+
+        let (set_number, way, ways) = locate(index, set);
+        self.ways = ways;
+        let set = self.set::<C>(set_number, ways);
+        if referenced {
+            set.referenced |= 1 << way;
+        } else {
+            set.referenced &= !(1 << way);
+        }
+    }
+}
+
+impl<V, C> Replacement<V, C> for ClockReplacement
+where
+    C: Capacity,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        if !self.referenced.is_empty() {
+            let candidates: Vec<_> = candidates.map(|(index, _)| index).collect();
+            // Like below, this terminates after at most `2 * candidates.len()`
+            // iterations.
+            let mut hand = self.hand % candidates.len();
+            let victim = loop {
+                let index = candidates[hand];
+                hand = (hand + 1) % candidates.len();
+                let referenced = per_slot::<_, C>(&mut self.referenced, index);
+                if !*referenced {
+                    break index;
+                }
+                *referenced = false;
+            };
+            self.hand = hand;
+            return victim;
+        }
+
+        let ways = set_ways(self.ways, candidates.len());
+        let mut candidates = candidates.map(|(index, _)| index).peekable();
+        let set_number = *candidates.peek().unwrap() / ways;
+        let set = self.set::<C>(set_number, ways);
+
+        // The hand skips over any ways that are not candidates.
+        let candidate_ways =
+            candidates.fold(0_u32, |ways_mask, index| ways_mask | 1 << (index % ways));
+
+        // This loop terminates after at most `2 * ways` iterations, since every
+        // iteration either finds the victim, clears a reference bit, or skips
+        // a way that is not a candidate.
+        let victim = loop {
+            let hand = set.hand;
+            set.hand = (hand + 1) % ways;
+            if candidate_ways & (1 << hand) == 0 {
+                continue;
+            }
+            if set.referenced & (1 << hand) == 0 {
+                break hand;
+            }
+            set.referenced &= !(1 << hand);
+        };
+
+        set_number * ways + victim
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.set_referenced::<C>(index, set, true);
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.set_referenced::<C>(index, set, false);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        _new: &V,
+    ) {
+        self.set_referenced::<C>(index, set, false);
+    }
+
+    #[inline]
+    fn on_remove(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.set_referenced::<C>(index, set, false);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.sets.clear();
+        self.ways = 0;
+        self.hand = 0;
+        self.referenced.clear();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{choose, hit, insert};
+    use crate::{AssociativeCache, Capacity16, Capacity4, Capacity8, PointerFourWay, SkewedTwoWay};
+
+    #[test]
+    fn clock_victim() {
+        let replacement = &mut ClockReplacement::default();
+        let choose_among =
+            |r: &mut ClockReplacement, ways: &[usize]| choose::<Capacity4, _>(r, ways);
+        let choose = |r: &mut ClockReplacement| choose_among(r, &[0, 1, 2, 3]);
+        let hit = |r: &mut ClockReplacement, index| hit::<Capacity4, _>(r, index, &[0, 1, 2, 3]);
+
+        // Nothing is referenced, so the hand just goes around in order.
+        assert_eq!(choose(replacement), 0);
+        assert_eq!(choose(replacement), 1);
+
+        // Ways 2 and 3 get a second chance.
+        hit(replacement, 2);
+        hit(replacement, 3);
+        assert_eq!(choose(replacement), 0);
+
+        // But not a third chance.
+        assert_eq!(choose(replacement), 1);
+        assert_eq!(choose(replacement), 2);
+        assert_eq!(choose(replacement), 3);
+
+        // When everything is referenced, the hand's way is chosen after a full
+        // sweep.
+        for i in 0..4 {
+            hit(replacement, i);
+        }
+        assert_eq!(choose(replacement), 0);
+
+        // The hand skips over ways that are not candidates.
+        hit(replacement, 1);
+        assert_eq!(choose_among(replacement, &[1, 3]), 3);
+        assert_eq!(choose_among(replacement, &[2]), 2);
+    }
+
+    #[test]
+    fn clock_per_set_hands() {
+        let mut cache = AssociativeCache::<
+            *mut u8,
+            usize,
+            Capacity8,
+            PointerFourWay,
+            ClockReplacement,
+        >::default();
+
+        // Fill both sets: even keys go in the first set and odd keys in the
+        // second, from the last way to the first.
+        for i in 0..8 {
+            assert_eq!(cache.insert(i as *mut _, i), None);
+        }
+
+        // Replacing in the second set doesn't advance the first set's hand.
+        assert_eq!(cache.insert(9 as *mut _, 9), Some((7 as *mut _, 7)));
+        assert_eq!(cache.insert(11 as *mut _, 11), Some((5 as *mut _, 5)));
+        assert_eq!(cache.insert(8 as *mut _, 8), Some((6 as *mut _, 6)));
+
+        // Key 4 is in way 1 of the first set, which the hand is pointing at,
+        // and it gets a second chance.
+        assert_eq!(cache.get(&(4 as *mut _)), Some(&4));
+        assert_eq!(cache.insert(10 as *mut _, 10), Some((2 as *mut _, 2)));
+        assert_eq!(cache.insert(12 as *mut _, 12), Some((0 as *mut _, 0)));
+        assert_eq!(cache.insert(14 as *mut _, 14), Some((8 as *mut _, 8)));
+    }
+
+    #[test]
+    fn clock_skewed() {
+        let mut cache =
+            AssociativeCache::<u64, u64, Capacity16, SkewedTwoWay, ClockReplacement>::default();
+        for key in 0..2000 {
+            if cache.get(&key).is_none() {
+                cache.insert(key, key);
+            }
+            assert_eq!(cache.get(&key), Some(&key));
+        }
+        assert!(cache.replacement_policy().sets.is_empty());
+
+        // The hand only visits candidates, and referenced candidates get a
+        // second chance.
+        let policy = &mut ClockReplacement::default();
+        insert::<Capacity16, _>(policy, 3, &[3, 9]);
+        insert::<Capacity16, _>(policy, 9, &[3, 9]);
+        insert::<Capacity16, _>(policy, 12, &[5, 12]);
+        assert_eq!(choose::<Capacity16, _>(policy, &[3, 9]), 3);
+        assert_eq!(choose::<Capacity16, _>(policy, &[3, 9]), 9);
+        hit::<Capacity16, _>(policy, 5, &[5, 12]);
+        assert_eq!(choose::<Capacity16, _>(policy, &[5, 12]), 12);
+        assert_eq!(choose::<Capacity16, _>(policy, &[5, 12]), 5);
+    }
+}
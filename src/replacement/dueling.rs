@@ -0,0 +1,365 @@
+//! Set dueling between two replacement policies.
+//!
+//! See "Adaptive Insertion Policies for High Performance Caching" by Qureshi et
+//! al. for details.
+
+use super::*;
+
+/// The largest value of the 10-bit policy selection counter.
+const PSEL_MAX: u16 = (1 << 10) - 1;
+
+/// One out of every this many sets is a leader set for each policy.
+const LEADER_INTERVAL: usize = 32;
+
+/// Adaptively choose between two replacement policies with set dueling.
+///
+/// A few leader sets are dedicated to each of the `first` and `second`
+/// policies, and always use that policy to choose entries to replace. A
+/// saturating policy selection counter (PSEL) is incremented on every miss in
+/// one of `first`'s leader sets, and decremented on every miss in one of
+/// `second`'s leader sets. Misses are observed through `on_insert` and
+/// `on_replace`; re-inserting a key that is already cached is not a miss. All
+/// of the remaining follower sets use whichever policy is currently missing
+/// less often, according to the PSEL.
+///
+/// Out of every 32 sets, the first is a leader set for `first` and the second
+/// is a leader set for `second`. Sets are numbered by their first slot's index
+/// divided by their associativity. For sets that are not aligned ranges of
+/// contiguous slots, such as those of `SkewedTwoWay` and `SkewedFourWay`, this
+/// means that every set whose first slot is in the same group of slots shares
+/// the same leader status, which still dedicates a fixed sample of the sets to
+/// each policy. The PSEL is a 10-bit counter, and follower sets use `second`
+/// when it is in the upper half of its range.
+///
+/// Both policies are notified of every hit, insertion, replacement, and
+/// removal in every set, so that they each have up-to-date metadata whenever
+/// they are asked to choose an entry to replace.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// // Dynamic re-reference interval prediction (DRRIP) is set dueling between
+/// // SRRIP and BRRIP.
+/// let cache = AssociativeCache::<
+///     String,
+///     usize,
+///     Capacity8192,
+///     HashEightWay,
+///     SetDueling<SrripReplacement, BrripReplacement>,
+/// >::default();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SetDueling<A, B> {
+    first: A,
+    second: B,
+    psel: u16,
+    // The associativity of the sets seen so far, or zero if none have been.
+    ways: usize,
+}
+
+/// Dynamic re-reference interval prediction (DRRIP) cache replacement.
+///
+/// Set dueling between `SrripReplacement` and `BrripReplacement`, which adapts
+/// to both scanning and thrashing access patterns.
+pub type DrripReplacement = SetDueling<SrripReplacement, BrripReplacement>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Leader {
+    First,
+    Second,
+    Follower,
+}
+
+impl<A, B> Default for SetDueling<A, B>
+where
+    A: Default,
+    B: Default,
+{
+    #[inline]
+    fn default() -> Self {
+        SetDueling::with_policies(A::default(), B::default())
+    }
+}
+
+impl<A, B> SetDueling<A, B> {
+    /// Construct a `SetDueling` between the `first` and `second` replacement
+    /// policies.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// let policy = SetDueling::with_policies(
+    ///     SrripReplacement::with_rrpv_bits(3),
+    ///     BrripReplacement::with_rrpv_bits(3),
+    /// );
+    /// ```
+    #[inline]
+    pub fn with_policies(first: A, second: B) -> Self {
+        SetDueling {
+            first,
+            second,
+            psel: PSEL_MAX / 2,
+            ways: 0,
+        }
+    }
+
+    /// Get a shared reference to the first policy.
+    #[inline]
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+
+    /// Get a shared reference to the second policy.
+    #[inline]
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+
+    #[inline]
+    fn leader(set_number: usize) -> Leader {
+        match set_number % LEADER_INTERVAL {
+            0 => Leader::First,
+            1 => Leader::Second,
+            _ => Leader::Follower,
+        }
+    }
+
+    #[inline]
+    fn uses_second(&self, set_number: usize) -> bool {
+        match Self::leader(set_number) {
+            Leader::First => false,
+            Leader::Second => true,
+            Leader::Follower => self.psel > PSEL_MAX / 2,
+        }
+    }
+
+    #[inline]
+    fn on_miss(&mut self, index: usize, set: impl ExactSizeIterator<Item = usize>) {
+        let (set_number, _, ways) = locate(index, set);
+        self.ways = ways;
+        match Self::leader(set_number) {
+            Leader::First => self.psel = (self.psel + 1).min(PSEL_MAX),
+            Leader::Second => self.psel = self.psel.saturating_sub(1),
+            Leader::Follower => {}
+        }
+    }
+}
+
+impl<V, C, A, B> Replacement<V, C> for SetDueling<A, B>
+where
+    C: Capacity,
+    A: Replacement<V, C>,
+    B: Replacement<V, C>,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        // The candidates might not be all of the set's slots, so prefer the
+        // associativity observed from the hooks.
+        let ways = set_ways(self.ways, candidates.len());
+        let mut candidates = candidates.peekable();
+        let first = candidates.peek().unwrap().0;
+        if self.uses_second(first / ways) {
+            self.second.choose_for_replacement(candidates)
+        } else {
+            self.first.choose_for_replacement(candidates)
+        }
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        self.first.on_hit(index, set.clone(), value);
+        self.second.on_hit(index, set, value);
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        self.on_miss(index, set.clone());
+        self.first.on_insert(index, set.clone(), value);
+        self.second.on_insert(index, set, value);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        old: &V,
+        new: &V,
+    ) {
+        self.on_miss(index, set.clone());
+        self.first.on_replace(index, set.clone(), old, new);
+        self.second.on_replace(index, set, old, new);
+    }
+
+    #[inline]
+    fn on_update(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        old: &V,
+        new: &V,
+    ) {
+        self.first.on_update(index, set.clone(), old, new);
+        self.second.on_update(index, set, old, new);
+    }
+
+    #[inline]
+    fn on_remove(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        self.first.on_remove(index, set.clone(), value);
+        self.second.on_remove(index, set, value);
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        self.first.on_relocate(from, to, set.clone(), value);
+        self.second.on_relocate(from, to, set, value);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.first.on_clear();
+        self.second.on_clear();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{choose, hits, insert};
+    use crate::{AssociativeCache, Capacity128, PointerFourWay};
+
+    #[derive(Debug, Default)]
+    struct FirstWay;
+
+    impl<V, C: Capacity> Replacement<V, C> for FirstWay {
+        fn choose_for_replacement<'a>(
+            &mut self,
+            mut candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+        ) -> usize
+        where
+            V: 'a,
+        {
+            candidates.next().unwrap().0
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct LastWay;
+
+    impl<V, C: Capacity> Replacement<V, C> for LastWay {
+        fn choose_for_replacement<'a>(
+            &mut self,
+            candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+        ) -> usize
+        where
+            V: 'a,
+        {
+            candidates.last().unwrap().0
+        }
+    }
+
+    #[test]
+    fn set_dueling() {
+        type Policy = SetDueling<FirstWay, LastWay>;
+        let policy = &mut Policy::default();
+        let choose =
+            |p: &mut Policy, set: usize| choose::<Capacity128, _>(p, &[2 * set, 2 * set + 1]);
+        let miss = |p: &mut Policy, set: usize| {
+            insert::<Capacity128, _>(p, 2 * set, &[2 * set, 2 * set + 1]);
+        };
+
+        // Leader sets always use their own policy, and followers start out
+        // using the first policy.
+        assert_eq!(choose(policy, 0), 0);
+        assert_eq!(choose(policy, 1), 3);
+        assert_eq!(choose(policy, 2), 4);
+        assert_eq!(choose(policy, 33), 67);
+
+        // Misses in the first policy's leader sets switch followers over to the
+        // second policy.
+        miss(policy, 0);
+        assert_eq!(choose(policy, 2), 5);
+        assert_eq!(choose(policy, 32), 64);
+
+        // Misses in follower sets don't affect the selection.
+        for _ in 0..10 {
+            miss(policy, 2);
+        }
+        assert_eq!(choose(policy, 2), 5);
+
+        // Misses in the second policy's leader sets switch followers back.
+        miss(policy, 1);
+        miss(policy, 33);
+        assert_eq!(choose(policy, 2), 4);
+        assert_eq!(choose(policy, 1), 3);
+
+        // The selection counter saturates.
+        for _ in 0..2 * PSEL_MAX {
+            miss(policy, 1);
+        }
+        assert_eq!(policy.psel, 0);
+        miss(policy, 0);
+        assert_eq!(choose(policy, 2), 4);
+    }
+
+    #[test]
+    fn set_dueling_updates_are_not_misses() {
+        type Policy = SetDueling<FirstWay, LastWay>;
+        let mut cache =
+            AssociativeCache::<*mut u8, usize, Capacity128, PointerFourWay, Policy>::default();
+
+        // Key 0 is in the first policy's first leader set.
+        cache.insert(0 as *mut _, 0);
+        let psel = cache.replacement_policy().psel;
+        assert_eq!(psel, PSEL_MAX / 2 + 1);
+        for i in 1..10 {
+            cache.insert(0 as *mut _, i);
+        }
+        assert_eq!(cache.replacement_policy().psel, psel);
+    }
+
+    #[test]
+    fn drrip_adapts_to_thrashing() {
+        type Cache<R> = AssociativeCache<*mut u8, usize, Capacity128, PointerFourWay, R>;
+
+        // A cyclic working set that is twice as large as the cache.
+        let trace = || (0..100).flat_map(|_| 0..256);
+        let srrip = hits(&mut Cache::<SrripReplacement>::default(), trace());
+        let brrip = hits(&mut Cache::<BrripReplacement>::default(), trace());
+        let drrip = hits(&mut Cache::<DrripReplacement>::default(), trace());
+        assert_eq!(srrip, 0);
+        assert!(drrip > brrip * 9 / 10, "{} vs {}", drrip, brrip);
+    }
+}
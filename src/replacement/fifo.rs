@@ -0,0 +1,137 @@
+//! First in, first out (FIFO) replacement policy implementation.
+
+use super::*;
+
+/// First in, first out (FIFO) cache replacement.
+///
+/// When choosing an entry to replace, the candidate that was inserted longest
+/// ago is the victim. Hits do not affect the order. Inserting a new value for a
+/// key that is already in the cache counts as a new insertion of that key.
+///
+/// The insertion order is tracked per slot inside the policy, so cache values
+/// do not need to be wrapped. Unlike `RoundRobinReplacement`, which shares a
+/// single counter between all sets, the victim is always the oldest entry in
+/// the key's own set, regardless of what happens in other sets.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let cache = AssociativeCache::<
+///     String,
+///     usize,
+///     Capacity128,
+///     HashEightWay,
+///     FifoReplacement,
+/// >::default();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FifoReplacement {
+    // The number of insertions so far.
+    insertions: u64,
+    // The value of `insertions` when each slot's entry was inserted.
+    inserted_at: Vec<u64>,
+}
+
+impl FifoReplacement {
+    #[inline]
+    fn insert<C: Capacity>(&mut self, index: usize) {
+        *per_slot::<_, C>(&mut self.inserted_at, index) = self.insertions;
+        self.insertions += 1;
+    }
+}
+
+impl<V, C> Replacement<V, C> for FifoReplacement
+where
+    C: Capacity,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        let mut oldest = None;
+        for (index, _) in candidates {
+            let inserted_at = *per_slot::<_, C>(&mut self.inserted_at, index);
+            oldest = match oldest {
+                Some((t, i)) if t <= inserted_at => Some((t, i)),
+                _ => Some((inserted_at, index)),
+            };
+        }
+        oldest.unwrap().1
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.insert::<C>(index);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        _new: &V,
+    ) {
+        self.insert::<C>(index);
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        relocate_per_slot::<_, C>(&mut self.inserted_at, from, to);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.inserted_at.clear();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::{AssociativeCache, Capacity8, PointerFourWay};
+
+    #[test]
+    fn fifo_per_set() {
+        let mut cache = AssociativeCache::<
+            *mut u8,
+            usize,
+            Capacity8,
+            PointerFourWay,
+            FifoReplacement,
+        >::default();
+
+        // Even keys go in the first set, and odd keys in the second.
+        for i in [0, 2, 4, 6, 1, 3, 5, 7] {
+            assert_eq!(cache.insert(i as *mut _, i), None);
+        }
+
+        // Hits don't change the order.
+        assert_eq!(cache.get(&(0 as *mut _)), Some(&0));
+
+        // Replacements in one set don't affect the other.
+        assert_eq!(cache.insert(9 as *mut _, 9), Some((1 as *mut _, 1)));
+        assert_eq!(cache.insert(11 as *mut _, 11), Some((3 as *mut _, 3)));
+        assert_eq!(cache.insert(8 as *mut _, 8), Some((0 as *mut _, 0)));
+        assert_eq!(cache.insert(10 as *mut _, 10), Some((2 as *mut _, 2)));
+        assert_eq!(cache.insert(13 as *mut _, 13), Some((5 as *mut _, 5)));
+    }
+}
@@ -0,0 +1,242 @@
+//! GreedyDual-Size cost-aware replacement policy implementation and a trait
+//! for reporting the cost of cache values.
+
+use super::*;
+
+/// A trait for cache values that know how expensive they are to recompute, for
+/// use with cost-aware cache replacement policies.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// struct Compiled {
+///     code: Vec<u8>,
+///     compile_micros: u64,
+/// }
+///
+/// impl Cost for Compiled {
+///     fn cost(&self) -> u64 {
+///         self.compile_micros
+///     }
+///
+///     fn size(&self) -> u64 {
+///         self.code.len() as u64
+///     }
+/// }
+/// ```
+pub trait Cost {
+    /// The cost of recomputing this value if it is replaced, in any unit, as
+    /// long as it is consistent across all of the cache's values.
+    fn cost(&self) -> u64;
+
+    /// The size of this value, in any unit, as long as it is consistent across
+    /// all of the cache's values.
+    ///
+    /// Defaults to one, which makes replacement depend only on cost.
+    fn size(&self) -> u64 {
+        1
+    }
+}
+
+/// GreedyDual-Size cost-aware cache replacement.
+///
+/// Every slot has a priority `H = L + cost / size`, which is computed when its
+/// entry is inserted and restored whenever the entry is hit. When choosing an
+/// entry to replace, the candidate with the lowest `H` is the victim, and the
+/// cache-wide inflation value `L` is raised to the victim's `H`. Entries that
+/// are expensive to recompute (relative to their size) stay in the cache
+/// longer, while inflating `L` lets entries that have not been hit in a long
+/// time eventually be replaced, no matter their cost.
+///
+/// Requires that the cache value type implement `Cost`. A value whose size is
+/// zero is treated as if its size were one.
+///
+/// See "Cost-Aware WWW Proxy Caching Algorithms" by Cao and Irani for details.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// struct Expensive(u64);
+///
+/// impl Cost for Expensive {
+///     fn cost(&self) -> u64 {
+///         self.0
+///     }
+/// }
+///
+/// let cache = AssociativeCache::<
+///     String,
+///     Expensive,
+///     Capacity128,
+///     HashEightWay,
+///     GreedyDualReplacement,
+/// >::default();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct GreedyDualReplacement {
+    // The inflation value, `L`.
+    inflation: f64,
+    // Each slot's priority, `H`.
+    priorities: Vec<f64>,
+}
+
+impl GreedyDualReplacement {
+    #[inline]
+    fn set_priority<V: Cost, C: Capacity>(&mut self, index: usize, value: &V) {
+        let priority = self.inflation + value.cost() as f64 / value.size().max(1) as f64;
+        *per_slot::<_, C>(&mut self.priorities, index) = priority;
+    }
+}
+
+impl<V, C> Replacement<V, C> for GreedyDualReplacement
+where
+    C: Capacity,
+    V: Cost,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        let mut victim = None;
+        for (index, _) in candidates {
+            let priority = *per_slot::<_, C>(&mut self.priorities, index);
+            victim = match victim {
+                Some((h, i)) if h <= priority => Some((h, i)),
+                _ => Some((priority, index)),
+            };
+        }
+        let (priority, index) = victim.unwrap();
+        self.inflation = priority;
+        index
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        self.set_priority::<V, C>(index, value);
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        self.set_priority::<V, C>(index, value);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        new: &V,
+    ) {
+        self.set_priority::<V, C>(index, new);
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        relocate_per_slot::<_, C>(&mut self.priorities, from, to);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.inflation = 0.0;
+        self.priorities.clear();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::{AssociativeCache, Capacity2, PointerTwoWay};
+
+    #[derive(Debug, PartialEq)]
+    struct Value {
+        cost: u64,
+        size: u64,
+    }
+
+    impl Cost for Value {
+        fn cost(&self) -> u64 {
+            self.cost
+        }
+
+        fn size(&self) -> u64 {
+            self.size
+        }
+    }
+
+    fn value(cost: u64, size: u64) -> Value {
+        Value { cost, size }
+    }
+
+    #[test]
+    fn greedy_dual_replacement() {
+        let mut cache = AssociativeCache::<
+            *mut u8,
+            Value,
+            Capacity2,
+            PointerTwoWay,
+            GreedyDualReplacement,
+        >::default();
+
+        // An expensive entry survives a bunch of cheap entries, until the
+        // inflation value catches up with it.
+        cache.insert(0 as *mut _, value(10, 1));
+        cache.insert(1 as *mut _, value(1, 1));
+        for i in 2..12 {
+            let (k, _) = cache.insert(i as *mut _, value(1, 1)).unwrap();
+            assert_eq!(k, (i - 1) as *mut _);
+        }
+        assert_eq!(cache.replacement_policy().inflation, 10.0);
+        let (k, _) = cache.insert(12 as *mut _, value(1, 1)).unwrap();
+        assert_eq!(k, 0 as *mut _);
+    }
+
+    #[test]
+    fn greedy_dual_size() {
+        let mut cache = AssociativeCache::<
+            *mut u8,
+            Value,
+            Capacity2,
+            PointerTwoWay,
+            GreedyDualReplacement,
+        >::default();
+
+        // Cost per unit of size is what matters.
+        cache.insert(0 as *mut _, value(100, 50));
+        cache.insert(1 as *mut _, value(10, 1));
+        assert_eq!(
+            cache.insert(2 as *mut _, value(1, 1)).unwrap().0,
+            0 as *mut _
+        );
+        assert_eq!(cache.replacement_policy().inflation, 2.0);
+
+        // Hits restore an entry's priority relative to the current inflation.
+        assert!(cache.get(&(1 as *mut _)).is_some());
+        assert_eq!(cache.replacement_policy().priorities, vec![12.0, 3.0]);
+    }
+}
@@ -0,0 +1,238 @@
+//! Least frequently used (LFU) replacement policy implementation.
+
+use super::*;
+
+/// By default, counters are aged after this many accesses per slot in the
+/// cache.
+const DEFAULT_AGING_ACCESSES_PER_SLOT: usize = 16;
+
+/// Least frequently used (LFU) cache replacement.
+///
+/// Every slot has a saturating 8-bit access counter, which is set to one when
+/// an entry is inserted and incremented whenever the entry is hit. When
+/// choosing an entry to replace, the candidate with the smallest count is the
+/// victim, with ties broken in favor of the earliest way in the set.
+///
+/// So that entries which were popular a long time ago do not stay in the cache
+/// forever, every counter in the cache is periodically halved. By default, this
+/// happens after every `16 * C::CAPACITY` accesses (hits and insertions), but
+/// the interval can be configured with `LfuReplacement::with_aging_interval`.
+///
+/// This works well when the popularity of keys is strongly skewed, where
+/// recency-based policies like `LruReplacement` let a stream of unpopular keys
+/// flush out the popular ones.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let cache = AssociativeCache::<
+///     String,
+///     usize,
+///     Capacity128,
+///     HashEightWay,
+///     LfuReplacement,
+/// >::default();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LfuReplacement {
+    counts: Vec<u8>,
+    accesses: usize,
+    aging_interval: Option<usize>,
+}
+
+impl LfuReplacement {
+    /// Construct an `LfuReplacement` that halves every access counter after
+    /// every `accesses` hits and insertions.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// let cache = AssociativeCache::<
+    ///     String,
+    ///     usize,
+    ///     Capacity128,
+    ///     HashEightWay,
+    ///     _,
+    /// >::with_replacement_policy(LfuReplacement::with_aging_interval(1000));
+    /// ```
+    #[inline]
+    pub fn with_aging_interval(accesses: usize) -> Self {
+        assert!(accesses > 0);
+        LfuReplacement {
+            counts: vec![],
+            accesses: 0,
+            aging_interval: Some(accesses),
+        }
+    }
+
+    #[inline]
+    fn access<C: Capacity>(&mut self, index: usize, count: impl FnOnce(u8) -> u8) {
+        let slot = per_slot::<_, C>(&mut self.counts, index);
+        *slot = count(*slot);
+
+        self.accesses += 1;
+        let interval = self
+            .aging_interval
+            .unwrap_or(DEFAULT_AGING_ACCESSES_PER_SLOT * C::CAPACITY);
+        if self.accesses >= interval {
+            self.accesses = 0;
+            for count in &mut self.counts {
+                *count /= 2;
+            }
+        }
+    }
+}
+
+impl<V, C> Replacement<V, C> for LfuReplacement
+where
+    C: Capacity,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        let mut lfu = None;
+        for (index, _) in candidates {
+            let count = *per_slot::<_, C>(&mut self.counts, index);
+            lfu = match lfu {
+                Some((c, i)) if c <= count => Some((c, i)),
+                _ => Some((count, index)),
+            };
+        }
+        lfu.unwrap().1
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.access::<C>(index, |count| count.saturating_add(1));
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.access::<C>(index, |_| 1);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        _new: &V,
+    ) {
+        self.access::<C>(index, |_| 1);
+    }
+
+    #[inline]
+    fn on_remove(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        *per_slot::<_, C>(&mut self.counts, index) = 0;
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        relocate_per_slot::<_, C>(&mut self.counts, from, to);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.counts.clear();
+        self.accesses = 0;
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{choose, hit, hits, insert};
+    use crate::{AssociativeCache, Capacity4, PointerFourWay};
+
+    #[test]
+    fn lfu_victim() {
+        let replacement = &mut LfuReplacement::default();
+        let choose = |r: &mut LfuReplacement| choose::<Capacity4, _>(r, &[0, 1, 2, 3]);
+        let insert =
+            |r: &mut LfuReplacement, index| insert::<Capacity4, _>(r, index, &[0, 1, 2, 3]);
+        let hit = |r: &mut LfuReplacement, index| hit::<Capacity4, _>(r, index, &[0, 1, 2, 3]);
+
+        for i in 0..4 {
+            insert(replacement, i);
+        }
+
+        // Ties are broken by way order.
+        assert_eq!(choose(replacement), 0);
+
+        hit(replacement, 0);
+        hit(replacement, 0);
+        hit(replacement, 1);
+        hit(replacement, 3);
+        assert_eq!(choose(replacement), 2);
+
+        hit(replacement, 2);
+        hit(replacement, 2);
+        assert_eq!(choose(replacement), 1);
+    }
+
+    #[test]
+    fn lfu_aging() {
+        let replacement = &mut LfuReplacement::with_aging_interval(10);
+        let insert =
+            |r: &mut LfuReplacement, index| insert::<Capacity4, _>(r, index, &[0, 1, 2, 3]);
+        let hit = |r: &mut LfuReplacement, index| hit::<Capacity4, _>(r, index, &[0, 1, 2, 3]);
+
+        insert(replacement, 0);
+        insert(replacement, 1);
+        for _ in 0..7 {
+            hit(replacement, 0);
+        }
+        assert_eq!(replacement.counts, vec![8, 1, 0, 0]);
+
+        // The tenth access halves every counter.
+        hit(replacement, 1);
+        assert_eq!(replacement.counts, vec![4, 1, 0, 0]);
+    }
+
+    #[test]
+    fn lfu_keeps_popular_keys() {
+        let mut cache =
+            AssociativeCache::<*mut u8, usize, Capacity4, PointerFourWay, LfuReplacement>::default(
+            );
+
+        // Keys 0 through 3 are popular, and every other key is only used
+        // once. There are more keys than ways in each iteration, so LRU would
+        // never hit.
+        let trace = (0..100).flat_map(|i| vec![0, 1, 2, 3, 4 + i]);
+        // Three of the popular keys stay resident, and only the one-off keys
+        // and the fourth popular key compete for the remaining way.
+        assert_eq!(hits(&mut cache, trace), 297);
+    }
+}
@@ -1,4 +1,4 @@
-//! Least recently used (LRU) replacement policy implementation and traits for
+//! Least recently used (LRU) replacement policy implementations and traits for
 //! working with LRU timestamps.
 
 use super::*;
@@ -200,20 +200,165 @@ where
     }
 
     #[inline]
-    fn on_hit(&self, value: &V) {
+    fn on_hit(
+        &mut self,
+        _index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
         value.update_timestamp();
     }
 
     #[inline]
-    fn on_insert(&self, value: &V) {
+    fn on_insert(
+        &mut self,
+        _index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
         value.update_timestamp();
     }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        _index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        new: &V,
+    ) {
+        new.update_timestamp();
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        _from: usize,
+        _to: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        // The timestamp moves along with the value.
+    }
+}
+
+/// Least recently used (LRU) cache replacement driven by a logical clock.
+///
+/// When considering which one of N cache values to replace, choose the one that
+/// was least recently used.
+///
+/// Unlike `LruReplacement`, this does not require that the cache value type
+/// implement `LruTimestamp`. Instead, the policy owns a `u64` counter that is
+/// incremented on every hit and insertion, and records the counter's value for
+/// each slot when it is accessed. This avoids calling `Instant::now()` on every
+/// access, and because no two accesses ever get the same timestamp, the LRU
+/// order is exact and deterministic.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let cache = AssociativeCache::<
+///     String,
+///     usize,
+///     Capacity128,
+///     HashEightWay,
+///     CounterLruReplacement,
+/// >::default();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CounterLruReplacement {
+    // The number of accesses so far.
+    clock: u64,
+    // The value of `clock` when each slot's entry was last accessed.
+    last_used: Vec<u64>,
+}
+
+impl CounterLruReplacement {
+    #[inline]
+    fn touch<C: Capacity>(&mut self, index: usize) {
+        self.clock += 1;
+        *per_slot::<_, C>(&mut self.last_used, index) = self.clock;
+    }
+}
+
+impl<V, C> Replacement<V, C> for CounterLruReplacement
+where
+    C: Capacity,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        let mut lru = None;
+        for (index, _) in candidates {
+            let last_used = *per_slot::<_, C>(&mut self.last_used, index);
+            lru = match lru {
+                Some((t, i)) if t <= last_used => Some((t, i)),
+                _ => Some((last_used, index)),
+            };
+        }
+        lru.unwrap().1
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.touch::<C>(index);
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.touch::<C>(index);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        _new: &V,
+    ) {
+        self.touch::<C>(index);
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        relocate_per_slot::<_, C>(&mut self.last_used, from, to);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.last_used.clear();
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::zero_ptr)]
 mod tests {
     use super::*;
-    use crate::Capacity4;
+    use crate::{AssociativeCache, Capacity4, PointerFourWay};
     use std::time::Duration;
 
     #[test]
@@ -308,4 +453,30 @@ mod tests {
 
         assert_eq!(index, 3);
     }
+
+    #[test]
+    fn counter_lru_replacement() {
+        let mut cache = AssociativeCache::<
+            *mut u8,
+            usize,
+            Capacity4,
+            PointerFourWay,
+            CounterLruReplacement,
+        >::default();
+
+        for i in 0..4 {
+            assert_eq!(cache.insert(i as *mut _, i), None);
+        }
+
+        // Back-to-back accesses are still strictly ordered.
+        assert_eq!(cache.get(&(0 as *mut _)), Some(&0));
+        assert_eq!(cache.get(&(1 as *mut _)), Some(&1));
+        assert_eq!(cache.insert(4 as *mut _, 4), Some((2 as *mut _, 2)));
+        assert_eq!(cache.insert(5 as *mut _, 5), Some((3 as *mut _, 3)));
+        assert_eq!(cache.insert(6 as *mut _, 6), Some((0 as *mut _, 0)));
+
+        // Peeking doesn't count as a use.
+        assert_eq!(cache.peek(&(1 as *mut _)), Some(&1));
+        assert_eq!(cache.insert(7 as *mut _, 7), Some((1 as *mut _, 1)));
+    }
 }
@@ -0,0 +1,157 @@
+//! Most recently used (MRU) replacement policy implementation.
+
+use super::*;
+
+/// Most recently used (MRU) cache replacement.
+///
+/// When choosing an entry to replace, the candidate that was most recently hit
+/// or inserted is the victim.
+///
+/// This is the opposite of LRU, and it is only a good choice for cyclic access
+/// patterns, where a working set that is slightly larger than a set is looped
+/// over again and again. With LRU, every access in such a loop evicts the entry
+/// that is about to be accessed next, so nothing ever hits. With MRU, most of
+/// the working set stays in the cache, and only the most recent entries churn.
+///
+/// Like `CounterLruReplacement`, this counts accesses itself rather than
+/// relying on `Instant::now()`, so cache values do not need to implement
+/// `LruTimestamp`.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let cache = AssociativeCache::<
+///     String,
+///     usize,
+///     Capacity128,
+///     HashEightWay,
+///     MruReplacement,
+/// >::default();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MruReplacement {
+    // The number of accesses so far.
+    clock: u64,
+    // The value of `clock` when each slot's entry was last accessed.
+    last_used: Vec<u64>,
+}
+
+impl MruReplacement {
+    #[inline]
+    fn touch<C: Capacity>(&mut self, index: usize) {
+        self.clock += 1;
+        *per_slot::<_, C>(&mut self.last_used, index) = self.clock;
+    }
+}
+
+impl<V, C> Replacement<V, C> for MruReplacement
+where
+    C: Capacity,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        let mut mru = None;
+        for (index, _) in candidates {
+            let last_used = *per_slot::<_, C>(&mut self.last_used, index);
+            mru = match mru {
+                Some((t, i)) if t >= last_used => Some((t, i)),
+                _ => Some((last_used, index)),
+            };
+        }
+        mru.unwrap().1
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.touch::<C>(index);
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.touch::<C>(index);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        _new: &V,
+    ) {
+        self.touch::<C>(index);
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        relocate_per_slot::<_, C>(&mut self.last_used, from, to);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.last_used.clear();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::test_utils::hits;
+    use crate::{AssociativeCache, Capacity4, PointerFourWay};
+
+    #[test]
+    fn mru_victim() {
+        let mut cache =
+            AssociativeCache::<*mut u8, usize, Capacity4, PointerFourWay, MruReplacement>::default(
+            );
+
+        for i in 0..4 {
+            assert_eq!(cache.insert(i as *mut _, i), None);
+        }
+        assert_eq!(cache.get(&(1 as *mut _)), Some(&1));
+        assert_eq!(cache.insert(4 as *mut _, 4), Some((1 as *mut _, 1)));
+        assert_eq!(cache.insert(5 as *mut _, 5), Some((4 as *mut _, 4)));
+    }
+
+    #[test]
+    fn mru_beats_lru_on_cyclic_trace() {
+        type Cache<R> = AssociativeCache<*mut u8, usize, Capacity4, PointerFourWay, R>;
+
+        // Loop over five keys, which is one more than the set can hold.
+        let trace = || (0..100).flat_map(|_| 0..5);
+
+        // LRU always evicts the key that is about to be used next, while MRU
+        // only churns the most recently used way and keeps the rest of the
+        // keys cached.
+        assert_eq!(
+            hits(&mut Cache::<CounterLruReplacement>::default(), trace()),
+            0
+        );
+        assert_eq!(hits(&mut Cache::<MruReplacement>::default(), trace()), 372);
+    }
+}
@@ -0,0 +1,258 @@
+//! Not recently used (NRU) replacement policy implementation.
+
+use super::*;
+
+/// Not recently used (NRU) cache replacement, also known as bit-PLRU.
+///
+/// Each slot has a single reference bit, which is set whenever its entry is hit
+/// or inserted. When choosing an entry to replace, the first candidate whose
+/// reference bit is clear is the victim. Whenever every reference bit in a set
+/// would be set, all of the set's bits are cleared instead, including that of
+/// the entry that was just referenced.
+///
+/// This is much cheaper than `LruReplacement`, which calls `Instant::now()` on
+/// every hit, and does not require cache values to implement `LruTimestamp`.
+///
+/// See the [`replacement`](crate::replacement) module documentation for its
+/// associativity limit and how it handles skewed sets.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let cache = AssociativeCache::<
+///     String,
+///     usize,
+///     Capacity128,
+///     HashEightWay,
+///     NruReplacement,
+/// >::default();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NruReplacement {
+    // The reference bits for each set, where bit `i` is the reference bit for
+    // way `i`.
+    referenced: Vec<u32>,
+    // The associativity of the aligned sets seen so far, or zero if none have
+    // been.
+    ways: usize,
+    // The reference bit for each slot, for sets that are not aligned ranges.
+    referenced_slots: Vec<bool>,
+}
+
+impl NruReplacement {
+    #[inline]
+    fn all_ways(ways: usize) -> u32 {
+        assert!(
+            ways <= 32,
+            "`NruReplacement` requires an associativity no greater than 32"
+        );
+        u32::MAX >> (32 - ways)
+    }
+
+    #[inline]
+    fn reference<C: Capacity>(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+    ) {
+        if !is_aligned_range(set.clone()) {
+            *per_slot::<_, C>(&mut self.referenced_slots, index) = true;
+            if set
+                .clone()
+                .all(|i| *per_slot::<_, C>(&mut self.referenced_slots, i))
+            {
+                for i in set {
+                    *per_slot::<_, C>(&mut self.referenced_slots, i) = false;
+                }
+            }
+            return;
+        }
+
+        let (set_number, way, ways) = locate(index, set);
+        self.ways = ways;
+        let all = Self::all_ways(ways);
+        let bits = per_set::<_, C>(&mut self.referenced, set_number, ways);
+        *bits |= 1 << way;
+        if *bits == all {
+            *bits = 0;
+        }
+    }
+}
+
+impl<V, C> Replacement<V, C> for NruReplacement
+where
+    C: Capacity,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        if !self.referenced_slots.is_empty() {
+            let mut candidates = candidates.map(|(index, _)| index).peekable();
+            let first = *candidates.peek().unwrap();
+            let referenced = &mut self.referenced_slots;
+            return candidates
+                .find(|&index| !*per_slot::<_, C>(referenced, index))
+                .unwrap_or(first);
+        }
+
+        let ways = set_ways(self.ways, candidates.len());
+        Self::all_ways(ways);
+        let mut candidates = candidates.map(|(index, _)| index).peekable();
+        let first = *candidates.peek().unwrap();
+        let bits = *per_set::<_, C>(&mut self.referenced, first / ways, ways);
+        // If every candidate is recently used (which is only possible when only
+        // some of the set's ways are candidates), fall back to the first.
+        candidates
+            .find(|index| bits & (1 << (index % ways)) == 0)
+            .unwrap_or(first)
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.reference::<C>(index, set);
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.reference::<C>(index, set);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        _new: &V,
+    ) {
+        self.reference::<C>(index, set);
+    }
+
+    #[inline]
+    fn on_remove(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        if !is_aligned_range(set.clone()) {
+            *per_slot::<_, C>(&mut self.referenced_slots, index) = false;
+            return;
+        }
+
+        let (set_number, way, ways) = locate(index, set);
+        *per_set::<_, C>(&mut self.referenced, set_number, ways) &= !(1 << way);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.referenced.clear();
+        self.ways = 0;
+        self.referenced_slots.clear();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{choose, hit};
+    use crate::{AssociativeCache, Capacity16, Capacity4, Capacity8, PointerFourWay, SkewedTwoWay};
+
+    #[test]
+    fn nru_victim() {
+        let replacement = &mut NruReplacement::default();
+        let choose_among = |r: &mut NruReplacement, ways: &[usize]| choose::<Capacity4, _>(r, ways);
+        let choose = |r: &mut NruReplacement| choose_among(r, &[0, 1, 2, 3]);
+        let hit = |r: &mut NruReplacement, index| hit::<Capacity4, _>(r, index, &[0, 1, 2, 3]);
+
+        assert_eq!(choose(replacement), 0);
+        hit(replacement, 0);
+        assert_eq!(choose(replacement), 1);
+        hit(replacement, 2);
+        assert_eq!(choose(replacement), 1);
+        hit(replacement, 1);
+        assert_eq!(choose(replacement), 3);
+
+        // Setting the last bit clears every bit in the set, including its own.
+        hit(replacement, 3);
+        assert_eq!(choose(replacement), 0);
+        hit(replacement, 0);
+        assert_eq!(choose(replacement), 1);
+
+        // When only some of the set's ways are candidates, the first
+        // unreferenced candidate is chosen, or else the first candidate.
+        hit(replacement, 3);
+        assert_eq!(choose_among(replacement, &[0, 2, 3]), 2);
+        assert_eq!(choose_among(replacement, &[0, 3]), 0);
+    }
+
+    #[test]
+    fn nru_cache() {
+        let mut cache =
+            AssociativeCache::<*mut u8, usize, Capacity8, PointerFourWay, NruReplacement>::default(
+            );
+
+        // Fill the first set from the last way to the first. Referencing way 0
+        // sets the last clear bit, which clears every bit.
+        for i in [0, 2, 4, 6] {
+            assert_eq!(cache.insert(i as *mut _, i), None);
+        }
+
+        // Ways 1 and 2 are referenced, so way 0 is the victim.
+        assert_eq!(cache.get(&(4 as *mut _)), Some(&4));
+        assert_eq!(cache.get(&(2 as *mut _)), Some(&2));
+        assert_eq!(cache.insert(8 as *mut _, 8), Some((6 as *mut _, 6)));
+
+        // Ways 0, 1, and 2 are referenced, so way 3 is the victim.
+        assert_eq!(cache.insert(10 as *mut _, 10), Some((0 as *mut _, 0)));
+
+        // Referencing way 3 cleared every bit, so way 0 is the victim.
+        assert_eq!(cache.insert(12 as *mut _, 12), Some((8 as *mut _, 8)));
+    }
+
+    #[test]
+    fn nru_skewed() {
+        let mut cache =
+            AssociativeCache::<u64, u64, Capacity16, SkewedTwoWay, NruReplacement>::default();
+        for key in 0..2000 {
+            if cache.get(&key).is_none() {
+                cache.insert(key, key);
+            }
+            assert_eq!(cache.get(&key), Some(&key));
+        }
+        assert!(cache.replacement_policy().referenced.is_empty());
+
+        // Slots keep their own reference bits, which are all cleared when every
+        // slot in the referenced entry's set would be referenced.
+        let policy = &mut NruReplacement::default();
+        let hit =
+            |r: &mut NruReplacement, index, set: &[usize]| hit::<Capacity16, _>(r, index, set);
+        let choose = |r: &mut NruReplacement, set: &[usize]| choose::<Capacity16, _>(r, set);
+        hit(policy, 3, &[3, 9]);
+        assert_eq!(choose(policy, &[3, 9]), 9);
+        assert_eq!(choose(policy, &[3, 12]), 12);
+        hit(policy, 12, &[5, 12]);
+        assert_eq!(choose(policy, &[3, 12]), 3);
+        hit(policy, 9, &[3, 9]);
+        assert_eq!(choose(policy, &[3, 9]), 3);
+        assert_eq!(choose(policy, &[12, 9]), 9);
+    }
+}
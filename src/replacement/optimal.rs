@@ -0,0 +1,246 @@
+//! Belady's optimal (OPT) offline replacement policy implementation.
+
+use super::*;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Belady's optimal (OPT) cache replacement, for a known trace of future
+/// accesses.
+///
+/// When choosing an entry to replace, the candidate whose key will be accessed
+/// again farthest in the future (or never) is the victim. No other policy can
+/// have fewer misses on the same trace, which makes this an upper bound to
+/// compare practical policies against, rather than something to use in
+/// production.
+///
+/// The policy is constructed from the whole trace of keys that will be
+/// accessed, in order, and it then follows along with the cache's accesses: it
+/// assumes that every hit and every insertion is the next access in the trace.
+/// This holds as long as the cache is driven by looking up each key in the
+/// trace with `get` (or `get_mut`, or `entry`), and inserting it whenever the
+/// lookup misses. Accesses past the end of the trace are treated as never
+/// being accessed again.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let trace = vec![1, 2, 3, 1, 2, 4, 1, 2, 3, 4];
+///
+/// let mut cache = AssociativeCache::<
+///     *mut u8,
+///     usize,
+///     Capacity4,
+///     PointerFourWay,
+///     _,
+/// >::with_replacement_policy(OptimalReplacement::with_trace(trace.iter().copied()));
+///
+/// let mut misses = 0;
+/// for key in trace {
+///     if cache.get(&(key as *mut u8)).is_none() {
+///         misses += 1;
+///         cache.insert(key as *mut u8, key);
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OptimalReplacement {
+    // For each access in the trace, the position of the next access to the
+    // same key, or `usize::MAX` if there is none.
+    next_use: Vec<usize>,
+    // The position in the trace of the next access.
+    position: usize,
+    // For each slot, the position of the next access to its entry's key.
+    slot_next_use: Vec<usize>,
+}
+
+impl OptimalReplacement {
+    /// Construct an `OptimalReplacement` for the given trace of future key
+    /// accesses.
+    pub fn with_trace<K>(trace: impl IntoIterator<Item = K>) -> Self
+    where
+        K: Hash + Eq,
+    {
+        let trace: Vec<K> = trace.into_iter().collect();
+        let mut next_use = vec![usize::MAX; trace.len()];
+        let mut seen = HashMap::new();
+        for (position, key) in trace.iter().enumerate().rev() {
+            if let Some(next) = seen.insert(key, position) {
+                next_use[position] = next;
+            }
+        }
+        OptimalReplacement {
+            next_use,
+            position: 0,
+            slot_next_use: vec![],
+        }
+    }
+
+    /// Get the number of accesses from the trace that have happened so far.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    #[inline]
+    fn access<C: Capacity>(&mut self, index: usize) {
+        let next = self
+            .next_use
+            .get(self.position)
+            .copied()
+            .unwrap_or(usize::MAX);
+        *per_slot::<_, C>(&mut self.slot_next_use, index) = next;
+        self.position += 1;
+    }
+}
+
+impl<V, C> Replacement<V, C> for OptimalReplacement
+where
+    C: Capacity,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        let mut farthest = None;
+        for (index, _) in candidates {
+            let next = *per_slot::<_, C>(&mut self.slot_next_use, index);
+            farthest = match farthest {
+                Some((n, i)) if n >= next => Some((n, i)),
+                _ => Some((next, index)),
+            };
+        }
+        farthest.unwrap().1
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.access::<C>(index);
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.access::<C>(index);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        _new: &V,
+    ) {
+        self.access::<C>(index);
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        relocate_per_slot::<_, C>(&mut self.slot_next_use, from, to);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.slot_next_use.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{hits, Lcg};
+    use crate::{AssociativeCache, Capacity128, HashEightWay, Indices};
+    use std::ops::Range;
+
+    struct Capacity3;
+
+    impl Capacity for Capacity3 {
+        const CAPACITY: usize = 3;
+    }
+
+    #[derive(Default)]
+    struct FullyAssociative;
+
+    impl<K, C: Capacity> Indices<K, C> for FullyAssociative {
+        type Indices = Range<usize>;
+
+        fn indices(&self, _key: &K) -> Self::Indices {
+            0..C::CAPACITY
+        }
+    }
+
+    fn misses<C, I, R>(trace: &[usize], replacement_policy: R) -> usize
+    where
+        C: Capacity,
+        I: Default + Indices<usize, C>,
+        R: Replacement<usize, C>,
+    {
+        let mut cache =
+            AssociativeCache::<usize, usize, C, I, R>::with_replacement_policy(replacement_policy);
+        trace.len() - hits(&mut cache, trace.iter().copied())
+    }
+
+    #[test]
+    fn belady_textbook_trace() {
+        // The classic reference string from "Operating System Concepts" by
+        // Silberschatz et al., with three frames.
+        let trace = [7, 0, 1, 2, 0, 3, 0, 4, 2, 3, 0, 3, 2, 1, 2, 0, 1, 7, 0, 1];
+        let opt = OptimalReplacement::with_trace(trace.iter().copied());
+        assert_eq!(misses::<Capacity3, FullyAssociative, _>(&trace, opt), 9);
+        assert_eq!(
+            misses::<Capacity3, FullyAssociative, _>(&trace, CounterLruReplacement::default()),
+            12
+        );
+        assert_eq!(
+            misses::<Capacity3, FullyAssociative, _>(&trace, FifoReplacement::default()),
+            15
+        );
+    }
+
+    #[test]
+    fn optimal_is_a_lower_bound() {
+        // A deterministic trace over a skewed key space.
+        let trace: Vec<usize> = Lcg::default()
+            .take(10_000)
+            .map(|r| r % (1 + r % 512))
+            .collect();
+
+        let opt = misses::<Capacity128, HashEightWay, _>(
+            &trace,
+            OptimalReplacement::with_trace(trace.iter().copied()),
+        );
+        let lru = misses::<Capacity128, HashEightWay, _>(&trace, CounterLruReplacement::default());
+        let round_robin =
+            misses::<Capacity128, HashEightWay, _>(&trace, RoundRobinReplacement::default());
+        assert!(opt < lru, "{} vs {}", opt, lru);
+        assert!(opt < round_robin, "{} vs {}", opt, round_robin);
+
+        #[cfg(feature = "rand")]
+        {
+            let random =
+                misses::<Capacity128, HashEightWay, _>(&trace, RandomReplacement::default());
+            assert!(opt < random, "{} vs {}", opt, random);
+        }
+    }
+}
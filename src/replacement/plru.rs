@@ -0,0 +1,363 @@
+//! Tree pseudo-least recently used (tree-PLRU) replacement policy
+//! implementation.
+
+use super::*;
+
+/// Tree pseudo-least recently used (tree-PLRU) cache replacement.
+///
+/// Each set keeps a binary tree of `ways - 1` bits, where every internal node
+/// points towards the half of its subtree that was used less recently. On every
+/// hit or insertion, the nodes along the path to the accessed way are flipped
+/// to point away from it. When choosing an entry to replace, the bits are
+/// followed from the root down to a leaf, and that way is the victim. If only
+/// some of the set's ways are candidates, subtrees without any candidates are
+/// skipped on the way down.
+///
+/// This approximates LRU using only a few bits per set, which is how many real
+/// hardware caches implement replacement. Unlike `LruReplacement`, the metadata
+/// lives inside the policy, so cache values do not need to implement
+/// `LruTimestamp`.
+///
+/// See the [`replacement`](crate::replacement) module documentation for its
+/// associativity limit and how it handles skewed sets.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let cache = AssociativeCache::<
+///     String,
+///     usize,
+///     Capacity128,
+///     HashEightWay,
+///     TreePlruReplacement,
+/// >::default();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TreePlruReplacement {
+    // The tree bits for each set. Node `n` of a set's tree (where the root is
+    // node `1` and node `n`'s children are nodes `2n` and `2n + 1`) is stored
+    // in bit `n - 1`. A clear bit points to the left child, and a set bit
+    // points to the right child.
+    trees: Vec<u32>,
+    // The associativity of the aligned sets seen so far, or zero if none have
+    // been.
+    ways: usize,
+    // The number of accesses so far to slots in sets that are not aligned
+    // ranges.
+    clock: u64,
+    // The value of `clock` when each slot's entry was last accessed, for sets
+    // that are not aligned ranges.
+    last_used: Vec<u64>,
+}
+
+impl TreePlruReplacement {
+    #[inline]
+    fn levels(ways: usize) -> u32 {
+        assert!(
+            ways.is_power_of_two() && ways <= 32,
+            "`TreePlruReplacement` requires a power-of-two associativity no greater than 32"
+        );
+        ways.trailing_zeros()
+    }
+
+    /// Flip the bits along the path to `way` so that they point away from it,
+    /// or just record when the slot was used if its set has no tree.
+    #[inline]
+    fn touch<C: Capacity>(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+    ) {
+        if !is_aligned_range(set.clone()) {
+            self.clock += 1;
+            *per_slot::<_, C>(&mut self.last_used, index) = self.clock;
+            return;
+        }
+
+        let (set_number, way, ways) = locate(index, set);
+        self.ways = ways;
+        let levels = Self::levels(ways);
+        let tree = per_set::<_, C>(&mut self.trees, set_number, ways);
+        let mut node = 1;
+        for level in (0..levels).rev() {
+            let right = (way >> level) & 1;
+            if right == 1 {
+                *tree &= !(1 << (node - 1));
+            } else {
+                *tree |= 1 << (node - 1);
+            }
+            node = 2 * node + right;
+        }
+    }
+
+    /// Follow the bits from the root to the pseudo-least recently used way
+    /// among the `candidates`, where bit `i` is set if way `i` is a candidate.
+    #[inline]
+    fn victim<C: Capacity>(&mut self, set_number: usize, ways: usize, candidates: u32) -> usize {
+        let levels = Self::levels(ways);
+        let tree = *per_set::<_, C>(&mut self.trees, set_number, ways);
+        let mut node = 1;
+        let mut way = 0;
+        for level in (0..levels).rev() {
+            let mut right = ((tree >> (node - 1)) & 1) as usize;
+            // Never descend into a subtree without any candidates.
+            let subtree =
+                (u64::from(candidates) >> ((2 * way + right) << level)) & ((1 << (1 << level)) - 1);
+            if subtree == 0 {
+                right ^= 1;
+            }
+            way = 2 * way + right;
+            node = 2 * node + right;
+        }
+        way
+    }
+}
+
+impl<V, C> Replacement<V, C> for TreePlruReplacement
+where
+    C: Capacity,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        if !self.last_used.is_empty() {
+            let mut lru = None;
+            for (index, _) in candidates {
+                let last_used = *per_slot::<_, C>(&mut self.last_used, index);
+                lru = match lru {
+                    Some((t, i)) if t <= last_used => Some((t, i)),
+                    _ => Some((last_used, index)),
+                };
+            }
+            return lru.unwrap().1;
+        }
+
+        let ways = set_ways(self.ways, candidates.len());
+        Self::levels(ways);
+        let mut candidates = candidates.map(|(index, _)| index).peekable();
+        let set_number = *candidates.peek().unwrap() / ways;
+        let candidate_ways =
+            candidates.fold(0_u32, |ways_mask, index| ways_mask | 1 << (index % ways));
+        set_number * ways + self.victim::<C>(set_number, ways, candidate_ways)
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.touch::<C>(index, set);
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.touch::<C>(index, set);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        _new: &V,
+    ) {
+        self.touch::<C>(index, set);
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        if is_aligned_range(set.clone()) {
+            self.touch::<C>(to, set);
+        } else {
+            relocate_per_slot::<_, C>(&mut self.last_used, from, to);
+        }
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.trees.clear();
+        self.ways = 0;
+        self.last_used.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{choose, hit, Lcg};
+    use crate::{AssociativeCache, Capacity1, Capacity32, Capacity4, Indices, PointerFourWay};
+    use crate::{Capacity16, PointerDirectMapped, PointerEightWay, PointerTwoWay, SkewedTwoWay};
+
+    /// A straightforward reference model of a set-associative cache with
+    /// tree-PLRU replacement, where each set's tree is an explicit array of
+    /// node directions.
+    struct Model {
+        ways: usize,
+        slots: Vec<Option<usize>>,
+        // `trees[set][node]` is `true` when node `node` (in heap order,
+        // starting at `1`) points at its right subtree.
+        trees: Vec<Vec<bool>>,
+    }
+
+    impl Model {
+        fn new(capacity: usize, ways: usize) -> Self {
+            Model {
+                ways,
+                slots: vec![None; capacity],
+                trees: vec![vec![false; ways]; capacity / ways],
+            }
+        }
+
+        fn touch(&mut self, set: usize, way: usize) {
+            let tree = &mut self.trees[set];
+            let (mut lo, mut hi, mut node) = (0, self.ways, 1);
+            while hi - lo > 1 {
+                let mid = (lo + hi) / 2;
+                if way < mid {
+                    tree[node] = true;
+                    hi = mid;
+                    node *= 2;
+                } else {
+                    tree[node] = false;
+                    lo = mid;
+                    node = node * 2 + 1;
+                }
+            }
+        }
+
+        fn victim(&self, set: usize) -> usize {
+            let tree = &self.trees[set];
+            let (mut lo, mut hi, mut node) = (0, self.ways, 1);
+            while hi - lo > 1 {
+                let mid = (lo + hi) / 2;
+                if tree[node] {
+                    lo = mid;
+                    node = node * 2 + 1;
+                } else {
+                    hi = mid;
+                    node *= 2;
+                }
+            }
+            lo
+        }
+
+        /// Access `key` in `set`, returning the evicted key, if any.
+        fn access(&mut self, set: usize, key: usize) -> (bool, Option<usize>) {
+            let base = set * self.ways;
+            let ways = base..base + self.ways;
+            if let Some(i) = ways.clone().find(|&i| self.slots[i] == Some(key)) {
+                self.touch(set, i - base);
+                return (true, None);
+            }
+            // The cache fills the last empty slot in a set first.
+            if let Some(i) = ways.rev().find(|&i| self.slots[i].is_none()) {
+                self.slots[i] = Some(key);
+                self.touch(set, i - base);
+                return (false, None);
+            }
+            let way = self.victim(set);
+            let evicted = self.slots[base + way].replace(key);
+            self.touch(set, way);
+            (false, evicted)
+        }
+    }
+
+    fn check_against_model<C, I>(ways: usize)
+    where
+        C: Capacity,
+        I: Default + Indices<*mut u8, C>,
+    {
+        let mut cache = AssociativeCache::<*mut u8, usize, C, I, TreePlruReplacement>::default();
+        let mut model = Model::new(C::CAPACITY, ways);
+
+        for r in Lcg::default().take(10_000) {
+            let key = r % (C::CAPACITY * 3);
+            let ptr = key as *mut u8;
+            let set = cache.indices().indices(&ptr).next().unwrap() / ways;
+
+            let (hit, evicted) = model.access(set, key);
+            if hit {
+                assert_eq!(cache.get(&ptr), Some(&key));
+            } else {
+                assert!(cache.get(&ptr).is_none());
+                let actual = cache.insert(ptr, key).map(|(_, v)| v);
+                assert_eq!(actual, evicted);
+            }
+        }
+    }
+
+    #[test]
+    fn tree_plru_matches_model() {
+        check_against_model::<Capacity1, PointerDirectMapped>(1);
+        check_against_model::<Capacity4, PointerTwoWay>(2);
+        check_against_model::<Capacity4, PointerFourWay>(4);
+        check_against_model::<Capacity32, PointerFourWay>(4);
+        check_against_model::<Capacity32, PointerEightWay>(8);
+    }
+
+    #[test]
+    fn tree_plru_victim() {
+        let replacement = &mut TreePlruReplacement::default();
+        let choose_among =
+            |r: &mut TreePlruReplacement, ways: &[usize]| choose::<Capacity4, _>(r, ways);
+        let choose = |r: &mut TreePlruReplacement| choose_among(r, &[0, 1, 2, 3]);
+        let touch =
+            |r: &mut TreePlruReplacement, index| hit::<Capacity4, _>(r, index, &[0, 1, 2, 3]);
+
+        assert_eq!(choose(replacement), 0);
+        touch(replacement, 0);
+        assert_eq!(choose(replacement), 2);
+        touch(replacement, 2);
+        assert_eq!(choose(replacement), 1);
+        touch(replacement, 1);
+        assert_eq!(choose(replacement), 3);
+        touch(replacement, 3);
+        assert_eq!(choose(replacement), 0);
+
+        // Subtrees without any candidates are skipped.
+        assert_eq!(choose_among(replacement, &[2, 3]), 2);
+        assert_eq!(choose_among(replacement, &[1, 3]), 1);
+    }
+
+    #[test]
+    fn tree_plru_skewed() {
+        let mut cache =
+            AssociativeCache::<u64, u64, Capacity16, SkewedTwoWay, TreePlruReplacement>::default();
+        for key in 0..2000 {
+            if cache.get(&key).is_none() {
+                cache.insert(key, key);
+            }
+            assert_eq!(cache.get(&key), Some(&key));
+        }
+        assert!(cache.replacement_policy().trees.is_empty());
+
+        // The least recently used candidate is replaced.
+        let policy = &mut TreePlruReplacement::default();
+        hit::<Capacity16, _>(policy, 9, &[3, 9]);
+        hit::<Capacity16, _>(policy, 3, &[3, 9]);
+        hit::<Capacity16, _>(policy, 12, &[5, 12]);
+        assert_eq!(choose::<Capacity16, _>(policy, &[3, 9]), 9);
+        assert_eq!(choose::<Capacity16, _>(policy, &[3, 12]), 3);
+    }
+}
@@ -0,0 +1,231 @@
+//! A replacement policy wrapper that prefers to replace certain entries.
+
+use super::*;
+use std::fmt;
+
+/// Prefer to replace entries whose values match a predicate, and otherwise
+/// defer to an inner replacement policy.
+///
+/// When choosing an entry to replace, the candidates are first filtered by the
+/// `predicate`, and then the `inner` policy chooses among the candidates that
+/// matched. If none of the candidates match, then the `inner` policy chooses
+/// among all of them instead.
+///
+/// This makes it easy to, for example, keep dirty entries in the cache until
+/// they have been written back, by only preferring to replace clean entries,
+/// without writing a whole new replacement algorithm.
+///
+/// The `inner` policy is notified of every hit, insertion, replacement, and
+/// removal, exactly as if it were used directly. The `predicate` may be called
+/// more than once for each candidate, and it should give the same answer each
+/// time.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// struct Page {
+///     data: Vec<u8>,
+///     dirty: bool,
+/// }
+///
+/// let mut cache = AssociativeCache::<
+///     *mut u8,
+///     Page,
+///     Capacity4,
+///     PointerFourWay,
+///     _,
+/// >::with_replacement_policy(Prefer::with_predicate(
+///     CounterLruReplacement::default(),
+///     |page: &Page| !page.dirty,
+/// ));
+/// ```
+#[derive(Clone)]
+pub struct Prefer<P, F> {
+    inner: P,
+    predicate: F,
+}
+
+impl<P, F> fmt::Debug for Prefer<P, F>
+where
+    P: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Prefer")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P, F> Prefer<P, F> {
+    /// Construct a `Prefer` that prefers to replace entries whose values match
+    /// `predicate`, and uses the `inner` policy to choose among them.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// // Prefer replacing even values, in FIFO order.
+    /// let policy = Prefer::with_predicate(FifoReplacement::default(), |v: &usize| v % 2 == 0);
+    /// ```
+    #[inline]
+    pub fn with_predicate(inner: P, predicate: F) -> Self {
+        Prefer { inner, predicate }
+    }
+
+    /// Get a shared reference to the inner policy.
+    #[inline]
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Get an exclusive reference to the inner policy.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<V, C, P, F> Replacement<V, C> for Prefer<P, F>
+where
+    C: Capacity,
+    P: Replacement<V, C>,
+    F: FnMut(&V) -> bool,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        let predicate = &mut self.predicate;
+        let mut candidates: Vec<_> = candidates.collect();
+        if candidates.iter().any(|(_, v)| predicate(v)) {
+            candidates.retain(|(_, v)| predicate(v));
+        }
+        self.inner.choose_for_replacement(candidates.into_iter())
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        self.inner.on_hit(index, set, value);
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        self.inner.on_insert(index, set, value);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        old: &V,
+        new: &V,
+    ) {
+        self.inner.on_replace(index, set, old, new);
+    }
+
+    #[inline]
+    fn on_update(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        old: &V,
+        new: &V,
+    ) {
+        self.inner.on_update(index, set, old, new);
+    }
+
+    #[inline]
+    fn on_remove(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        self.inner.on_remove(index, set, value);
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        value: &V,
+    ) {
+        self.inner.on_relocate(from, to, set, value);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.inner.on_clear();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::{AssociativeCache, Capacity4, PointerFourWay};
+
+    #[test]
+    fn prefer_clean_entries() {
+        // Values are `(value, dirty)` pairs.
+        let mut cache = AssociativeCache::<
+            *mut u8,
+            (usize, bool),
+            Capacity4,
+            PointerFourWay,
+            _,
+        >::with_replacement_policy(Prefer::with_predicate(
+            CounterLruReplacement::default(),
+            |&(_, dirty): &(usize, bool)| !dirty,
+        ));
+
+        assert_eq!(cache.insert(0 as *mut _, (0, true)), None);
+        assert_eq!(cache.insert(1 as *mut _, (1, false)), None);
+        assert_eq!(cache.insert(2 as *mut _, (2, true)), None);
+        assert_eq!(cache.insert(3 as *mut _, (3, false)), None);
+
+        // The least recently used clean entry is replaced, even though key 0
+        // is the least recently used entry overall.
+        assert_eq!(
+            cache.insert(4 as *mut _, (4, true)),
+            Some((1 as *mut _, (1, false)))
+        );
+        assert_eq!(
+            cache.insert(5 as *mut _, (5, true)),
+            Some((3 as *mut _, (3, false)))
+        );
+
+        // With only dirty entries left, fall back to plain LRU.
+        assert_eq!(
+            cache.insert(6 as *mut _, (6, true)),
+            Some((0 as *mut _, (0, true)))
+        );
+
+        // Flushing key 4 makes it the preferred victim again.
+        cache.get_mut(&(4 as *mut _)).unwrap().1 = false;
+        assert_eq!(
+            cache.insert(7 as *mut _, (7, true)),
+            Some((4 as *mut _, (4, false)))
+        );
+    }
+}
@@ -0,0 +1,410 @@
+//! Re-reference interval prediction (RRIP) replacement policy implementations.
+//!
+//! See "High Performance Cache Replacement Using Re-Reference Interval
+//! Prediction (RRIP)" by Jaleel et al. for details.
+
+use super::*;
+
+/// The default number of bits in each slot's re-reference prediction value.
+const DEFAULT_RRPV_BITS: u8 = 2;
+
+/// `BrripReplacement` inserts with a long, rather than distant, re-reference
+/// prediction once every this many insertions.
+const BRRIP_LONG_INSERTION_INTERVAL: u32 = 32;
+
+/// The state shared by both SRRIP and BRRIP: an `M`-bit re-reference
+/// prediction value (RRPV) for every slot.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Rrip {
+    // The maximum RRPV, `2^M - 1`, which predicts a distant re-reference.
+    max_rrpv: u8,
+    rrpvs: Vec<u8>,
+}
+
+impl Rrip {
+    #[inline]
+    fn with_rrpv_bits(bits: u8) -> Self {
+        assert!(
+            (1..=8).contains(&bits),
+            "RRIP replacement requires between 1 and 8 RRPV bits"
+        );
+        Rrip {
+            max_rrpv: u8::MAX >> (8 - bits),
+            rrpvs: vec![],
+        }
+    }
+
+    /// The RRPV for a "long" re-reference interval prediction.
+    #[inline]
+    fn long(&self) -> u8 {
+        self.max_rrpv.saturating_sub(1)
+    }
+
+    /// The RRPV for a "distant" re-reference interval prediction.
+    #[inline]
+    fn distant(&self) -> u8 {
+        self.max_rrpv
+    }
+
+    #[inline]
+    fn set_rrpv<C: Capacity>(&mut self, index: usize, rrpv: u8) {
+        *per_slot::<_, C>(&mut self.rrpvs, index) = rrpv;
+    }
+
+    /// Choose the first candidate predicted to be re-referenced in the distant
+    /// future, aging every candidate in the set until there is one.
+    #[inline]
+    fn choose_for_replacement<C: Capacity>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = usize>,
+    ) -> usize {
+        let ways = candidates.len();
+        assert!(
+            ways <= 32,
+            "RRIP replacement requires an associativity no greater than 32"
+        );
+
+        let mut indices = [0; 32];
+        let mut victim = None;
+        for (way, index) in candidates.enumerate() {
+            indices[way] = index;
+            let rrpv = *per_slot::<_, C>(&mut self.rrpvs, index);
+            victim = match victim {
+                Some((v, _)) if v >= rrpv => victim,
+                _ => Some((rrpv, index)),
+            };
+        }
+        let (rrpv, victim) = victim.unwrap();
+
+        // Aging the set one step at a time until some candidate reaches the
+        // maximum RRPV is equivalent to aging it all at once by the difference
+        // between the maximum RRPV and the largest candidate RRPV.
+        let age = self.max_rrpv - rrpv;
+        if age > 0 {
+            for &index in &indices[..ways] {
+                *per_slot::<_, C>(&mut self.rrpvs, index) += age;
+            }
+        }
+
+        victim
+    }
+}
+
+/// Static re-reference interval prediction (SRRIP) cache replacement.
+///
+/// Every slot has an `M`-bit re-reference prediction value (RRPV). New entries
+/// are inserted with a "long" re-reference prediction (`2^M - 2`), and entries
+/// that are hit are promoted to a "near-immediate" prediction (`0`). When
+/// choosing an entry to replace, the first candidate with a "distant"
+/// prediction (`2^M - 1`) is the victim; if there is no such candidate, then
+/// every candidate in the set is aged until there is one.
+///
+/// Because new entries must be hit before they are predicted to be
+/// re-referenced sooner than existing entries that have been hit, a scan of
+/// one-off keys will not flush a set's frequently used entries the way that it
+/// would with LRU.
+///
+/// See the [`replacement`](crate::replacement) module documentation for its
+/// associativity limit.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// // Use three bits per RRPV, rather than the default of two.
+/// let cache = AssociativeCache::<
+///     String,
+///     usize,
+///     Capacity128,
+///     HashEightWay,
+///     _,
+/// >::with_replacement_policy(SrripReplacement::with_rrpv_bits(3));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SrripReplacement {
+    rrip: Rrip,
+}
+
+impl Default for SrripReplacement {
+    #[inline]
+    fn default() -> Self {
+        SrripReplacement::with_rrpv_bits(DEFAULT_RRPV_BITS)
+    }
+}
+
+impl SrripReplacement {
+    /// Construct an `SrripReplacement` that uses `M = bits` bits for each
+    /// slot's re-reference prediction value.
+    ///
+    /// The default is two bits. Panics if `bits` is not between 1 and 8.
+    #[inline]
+    pub fn with_rrpv_bits(bits: u8) -> Self {
+        SrripReplacement {
+            rrip: Rrip::with_rrpv_bits(bits),
+        }
+    }
+}
+
+impl<V, C> Replacement<V, C> for SrripReplacement
+where
+    C: Capacity,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        self.rrip
+            .choose_for_replacement::<C>(candidates.map(|(index, _)| index))
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.rrip.set_rrpv::<C>(index, 0);
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        let rrpv = self.rrip.long();
+        self.rrip.set_rrpv::<C>(index, rrpv);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        _new: &V,
+    ) {
+        let rrpv = self.rrip.long();
+        self.rrip.set_rrpv::<C>(index, rrpv);
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        relocate_per_slot::<_, C>(&mut self.rrip.rrpvs, from, to);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.rrip.rrpvs.clear();
+    }
+}
+
+/// Bimodal re-reference interval prediction (BRRIP) cache replacement.
+///
+/// This is the same as `SrripReplacement`, except that new entries are usually
+/// inserted with a "distant" re-reference prediction (`2^M - 1`), and only
+/// once every 32 insertions with a "long" prediction (`2^M - 2`).
+///
+/// This protects the cache against thrashing when the working set is larger
+/// than the cache: most new entries are replaced first, and a fraction of the
+/// working set stays resident long enough to be hit.
+///
+/// See the [`replacement`](crate::replacement) module documentation for its
+/// associativity limit.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let cache = AssociativeCache::<
+///     String,
+///     usize,
+///     Capacity128,
+///     HashEightWay,
+///     BrripReplacement,
+/// >::default();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BrripReplacement {
+    rrip: Rrip,
+    insertions: u32,
+}
+
+impl Default for BrripReplacement {
+    #[inline]
+    fn default() -> Self {
+        BrripReplacement::with_rrpv_bits(DEFAULT_RRPV_BITS)
+    }
+}
+
+impl BrripReplacement {
+    /// Construct a `BrripReplacement` that uses `M = bits` bits for each
+    /// slot's re-reference prediction value.
+    ///
+    /// The default is two bits. Panics if `bits` is not between 1 and 8.
+    #[inline]
+    pub fn with_rrpv_bits(bits: u8) -> Self {
+        BrripReplacement {
+            rrip: Rrip::with_rrpv_bits(bits),
+            insertions: 0,
+        }
+    }
+
+    #[inline]
+    fn insertion_rrpv(&mut self) -> u8 {
+        self.insertions = (self.insertions + 1) % BRRIP_LONG_INSERTION_INTERVAL;
+        if self.insertions == 0 {
+            self.rrip.long()
+        } else {
+            self.rrip.distant()
+        }
+    }
+}
+
+impl<V, C> Replacement<V, C> for BrripReplacement
+where
+    C: Capacity,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        self.rrip
+            .choose_for_replacement::<C>(candidates.map(|(index, _)| index))
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.rrip.set_rrpv::<C>(index, 0);
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        let rrpv = self.insertion_rrpv();
+        self.rrip.set_rrpv::<C>(index, rrpv);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        _new: &V,
+    ) {
+        let rrpv = self.insertion_rrpv();
+        self.rrip.set_rrpv::<C>(index, rrpv);
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        relocate_per_slot::<_, C>(&mut self.rrip.rrpvs, from, to);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.rrip.rrpvs.clear();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{choose, hit, hits, insert};
+    use crate::{AssociativeCache, Capacity4, CounterLruReplacement, PointerFourWay};
+
+    type Cache<R> = AssociativeCache<*mut u8, usize, Capacity4, PointerFourWay, R>;
+
+    #[test]
+    fn srrip_victim() {
+        let replacement = &mut SrripReplacement::default();
+        let choose = |r: &mut SrripReplacement| choose::<Capacity4, _>(r, &[0, 1, 2, 3]);
+        let insert =
+            |r: &mut SrripReplacement, index| insert::<Capacity4, _>(r, index, &[0, 1, 2, 3]);
+        let hit = |r: &mut SrripReplacement, index| hit::<Capacity4, _>(r, index, &[0, 1, 2, 3]);
+
+        for i in 0..4 {
+            insert(replacement, i);
+        }
+        hit(replacement, 0);
+        hit(replacement, 2);
+
+        // Every entry is aged once, and then the first distant entry is chosen.
+        assert_eq!(choose(replacement), 1);
+        assert_eq!(replacement.rrip.rrpvs, vec![1, 3, 1, 3]);
+
+        insert(replacement, 1);
+        assert_eq!(choose(replacement), 3);
+        assert_eq!(replacement.rrip.rrpvs, vec![1, 2, 1, 3]);
+    }
+
+    #[test]
+    fn rrpv_bits() {
+        assert_eq!(SrripReplacement::with_rrpv_bits(1).rrip.max_rrpv, 1);
+        assert_eq!(SrripReplacement::with_rrpv_bits(3).rrip.long(), 6);
+        assert_eq!(BrripReplacement::with_rrpv_bits(8).rrip.distant(), 255);
+    }
+
+    #[test]
+    fn srrip_is_scan_resistant() {
+        // A hot key that is used over and over, with a scan of six keys that
+        // are each only used once in between, which is more than the set can
+        // hold.
+        let trace = || (0..100).flat_map(|i| (0..1).chain(100 + 6 * i..106 + 6 * i));
+
+        // Hits promote the hot key to near-immediate re-reference, while the
+        // scanned keys are inserted with a long re-reference interval and are
+        // evicted first, so the hot key stays cached.
+        let mut srrip = Cache::<SrripReplacement>::default();
+        assert_eq!(hits(&mut srrip, trace()), 99);
+        assert_eq!(srrip.get(&(0 as *mut u8)), Some(&0));
+
+        // LRU evicts the hot key during every scan.
+        let mut lru = Cache::<CounterLruReplacement>::default();
+        assert_eq!(hits(&mut lru, trace()), 0);
+        assert_eq!(lru.get(&(0 as *mut u8)), None);
+    }
+
+    #[test]
+    fn brrip_is_thrash_resistant() {
+        // A cyclic working set that is twice as large as the set.
+        let trace = || (0..100).flat_map(|_| 0..8);
+        assert_eq!(hits(&mut Cache::<SrripReplacement>::default(), trace()), 0);
+        assert!(hits(&mut Cache::<BrripReplacement>::default(), trace()) > 250);
+    }
+}
@@ -0,0 +1,306 @@
+//! Segmented least recently used (SLRU) replacement policy implementation.
+
+use super::*;
+
+/// Segmented least recently used (SLRU) cache replacement.
+///
+/// Each set's entries are split into a probationary segment and a protected
+/// segment. New entries start out in the probationary segment, and are
+/// promoted to the protected segment on their second hit. When choosing an
+/// entry to replace, the least recently used probationary candidate is the
+/// victim, and only if every candidate is protected is the least recently used
+/// protected candidate chosen instead.
+///
+/// The protected segment holds a fixed share of each set's ways (by default,
+/// half of them; see `SlruReplacement::with_protected_ways`). When promoting an
+/// entry into a full protected segment, the least recently used protected entry
+/// is demoted back to being the most recently used probationary entry, and is
+/// promoted again on its next hit.
+///
+/// This gives scan resistance: a stream of keys that are each only used once
+/// can only ever displace probationary entries, so the protected entries that
+/// have proven themselves useful stay in the cache.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let cache = AssociativeCache::<
+///     String,
+///     usize,
+///     Capacity128,
+///     HashEightWay,
+///     SlruReplacement,
+/// >::default();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SlruReplacement {
+    protected_ways: usize,
+    of_ways: usize,
+    // The number of accesses so far.
+    clock: u64,
+    slots: Vec<SlruSlot>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct SlruSlot {
+    protected: bool,
+    // The number of times this slot's entry has been hit, saturating at two.
+    hits: u8,
+    // The value of `clock` when this slot's entry was last accessed.
+    last_used: u64,
+}
+
+impl Default for SlruReplacement {
+    #[inline]
+    fn default() -> Self {
+        SlruReplacement::with_protected_ways(1, 2)
+    }
+}
+
+impl SlruReplacement {
+    /// Construct an `SlruReplacement` where `protected_ways` out of every
+    /// `of_ways` ways of a set are in the protected segment.
+    ///
+    /// The split is scaled to the cache's actual associativity, rounding down,
+    /// so `with_protected_ways(2, 8)` protects two ways of an eight-way set,
+    /// and one way of a four-way set.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// let cache = AssociativeCache::<
+    ///     String,
+    ///     usize,
+    ///     Capacity128,
+    ///     HashEightWay,
+    ///     _,
+    /// >::with_replacement_policy(SlruReplacement::with_protected_ways(2, 8));
+    /// ```
+    #[inline]
+    pub fn with_protected_ways(protected_ways: usize, of_ways: usize) -> Self {
+        assert!(
+            protected_ways < of_ways,
+            "`SlruReplacement` must leave at least one way in the probationary segment"
+        );
+        SlruReplacement {
+            protected_ways,
+            of_ways,
+            clock: 0,
+            slots: vec![],
+        }
+    }
+
+    #[inline]
+    fn touch<C: Capacity>(&mut self, index: usize) {
+        self.clock += 1;
+        per_slot::<_, C>(&mut self.slots, index).last_used = self.clock;
+    }
+
+    /// Put a new entry at `index` into the probationary segment, without any
+    /// hits.
+    #[inline]
+    fn reset<C: Capacity>(&mut self, index: usize) {
+        let slot = per_slot::<_, C>(&mut self.slots, index);
+        slot.protected = false;
+        slot.hits = 0;
+    }
+
+    /// Promote the entry at `index` into the protected segment, demoting the
+    /// least recently used protected entry in its `set` if the segment is
+    /// full.
+    #[inline]
+    fn promote<C: Capacity>(&mut self, index: usize, set: impl ExactSizeIterator<Item = usize>) {
+        let limit = set.len() * self.protected_ways / self.of_ways;
+        if limit == 0 {
+            return;
+        }
+
+        let mut protected = 0;
+        let mut lru = None;
+        for i in set {
+            let slot = per_slot::<_, C>(&mut self.slots, i);
+            if slot.protected {
+                protected += 1;
+                lru = match lru {
+                    Some((t, j)) if t <= slot.last_used => Some((t, j)),
+                    _ => Some((slot.last_used, i)),
+                };
+            }
+        }
+        if protected >= limit {
+            let (_, demoted) = lru.unwrap();
+            per_slot::<_, C>(&mut self.slots, demoted).protected = false;
+            self.touch::<C>(demoted);
+        }
+
+        per_slot::<_, C>(&mut self.slots, index).protected = true;
+    }
+}
+
+impl<V, C> Replacement<V, C> for SlruReplacement
+where
+    C: Capacity,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        // Order by segment first, and then by recency.
+        let mut victim = None;
+        for (index, _) in candidates {
+            let slot = per_slot::<_, C>(&mut self.slots, index);
+            let key = (slot.protected, slot.last_used);
+            victim = match victim {
+                Some((k, i)) if k <= key => Some((k, i)),
+                _ => Some((key, index)),
+            };
+        }
+        victim.unwrap().1
+    }
+
+    #[inline]
+    fn on_hit(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        let slot = per_slot::<_, C>(&mut self.slots, index);
+        slot.hits = (slot.hits + 1).min(2);
+        if !slot.protected && slot.hits == 2 {
+            self.promote::<C>(index, set);
+        }
+        self.touch::<C>(index);
+    }
+
+    #[inline]
+    fn on_insert(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.reset::<C>(index);
+        self.touch::<C>(index);
+    }
+
+    #[inline]
+    fn on_replace(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _old: &V,
+        _new: &V,
+    ) {
+        self.reset::<C>(index);
+        self.touch::<C>(index);
+    }
+
+    #[inline]
+    fn on_remove(
+        &mut self,
+        index: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        self.reset::<C>(index);
+    }
+
+    #[inline]
+    fn on_relocate(
+        &mut self,
+        from: usize,
+        to: usize,
+        _set: impl ExactSizeIterator<Item = usize> + Clone,
+        _value: &V,
+    ) {
+        relocate_per_slot::<_, C>(&mut self.slots, from, to);
+    }
+
+    #[inline]
+    fn on_clear(&mut self) {
+        self.slots.clear();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::test_utils::hits;
+    use crate::{AssociativeCache, Capacity4, Capacity8, PointerEightWay, PointerFourWay};
+
+    #[test]
+    fn slru_promotion_and_demotion() {
+        let mut cache = AssociativeCache::<
+            *mut u8,
+            usize,
+            Capacity4,
+            PointerFourWay,
+            SlruReplacement,
+        >::default();
+
+        for i in 0..4 {
+            assert_eq!(cache.insert(i as *mut _, i), None);
+        }
+
+        // A single hit does not promote an entry.
+        assert_eq!(cache.get(&(3 as *mut _)), Some(&3));
+
+        // Two of the four ways are protected, so promoting a third entry
+        // demotes the least recently used protected entry.
+        for i in 0..3 {
+            assert_eq!(cache.get(&(i as *mut _)), Some(&i));
+            assert_eq!(cache.get(&(i as *mut _)), Some(&i));
+        }
+
+        // Key 3 is still probationary, and is the least recently used
+        // probationary entry, and then key 0 was demoted to be the most
+        // recently used probationary entry.
+        assert_eq!(cache.insert(4 as *mut _, 4), Some((3 as *mut _, 3)));
+        assert_eq!(cache.insert(5 as *mut _, 5), Some((0 as *mut _, 0)));
+        assert_eq!(cache.insert(6 as *mut _, 6), Some((4 as *mut _, 4)));
+
+        // Protected entries are only chosen when they are the only candidates.
+        // Keys 1 and 2 are in slots 2 and 1 respectively, since the cache
+        // fills each set from its last way to its first.
+        let values = [0; 4];
+        let candidates = [1, 2].iter().map(|&i| (i, &values[i]));
+        assert_eq!(
+            <SlruReplacement as Replacement<usize, Capacity4>>::choose_for_replacement(
+                cache.replacement_policy_mut(),
+                candidates,
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn slru_resists_scans() {
+        type Cache<R> = AssociativeCache<*mut u8, usize, Capacity8, PointerEightWay, R>;
+
+        // Keys 0 through 3 are used twice, and then each time they are used
+        // again, there is a scan of eight keys that are only used once.
+        let trace = || {
+            vec![0, 1, 2, 3, 0, 1, 2, 3].into_iter().chain(
+                (0..100).flat_map(|i| (0..4).chain((0..8).map(move |key| 100 + i * 8 + key))),
+            )
+        };
+
+        // LRU only hits until the first scan evicts the popular keys, while
+        // SLRU keeps them protected.
+        assert_eq!(
+            hits(&mut Cache::<CounterLruReplacement>::default(), trace()),
+            8
+        );
+        assert_eq!(hits(&mut Cache::<SlruReplacement>::default(), trace()), 404);
+    }
+}
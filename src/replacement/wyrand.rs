@@ -0,0 +1,155 @@
+//! Dependency-free random replacement policy implementation.
+
+use super::*;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Choose a random cache entry to replace, using a small built-in `wyrand`
+/// pseudo-random number generator.
+///
+/// When considering `n` items to potentially replace, choose one at random.
+///
+/// Unlike `RandomReplacement`, this does not require the `"rand"` feature. The
+/// generator is seeded with a single `u64`, and the same seed always makes the
+/// same sequence of choices, so a cache simulation can be replayed exactly by
+/// constructing its policy with `WyRandReplacement::with_seed` and the
+/// `seed()` of the original run.
+///
+/// The default policy is seeded differently each time it is constructed.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// let cache = AssociativeCache::<
+///     String,
+///     usize,
+///     Capacity128,
+///     HashEightWay,
+///     WyRandReplacement,
+/// >::default();
+///
+/// // Log the seed, so that this run can be replayed if something goes wrong.
+/// println!("seed = {}", cache.replacement_policy().seed());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WyRandReplacement {
+    seed: u64,
+    state: u64,
+}
+
+impl Default for WyRandReplacement {
+    #[inline]
+    fn default() -> Self {
+        // `RandomState` is randomly keyed for each process, and each new
+        // instance within a process gets different keys too.
+        let seed = RandomState::new().build_hasher().finish();
+        WyRandReplacement::with_seed(seed)
+    }
+}
+
+impl WyRandReplacement {
+    /// Construct a `WyRandReplacement` whose generator is seeded with `seed`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// let cache = AssociativeCache::<
+    ///     String,
+    ///     usize,
+    ///     Capacity128,
+    ///     HashEightWay,
+    ///     _,
+    /// >::with_replacement_policy(WyRandReplacement::with_seed(42));
+    /// ```
+    #[inline]
+    pub fn with_seed(seed: u64) -> Self {
+        WyRandReplacement { seed, state: seed }
+    }
+
+    /// Get the seed that this policy's generator was constructed with.
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0xa076_1d64_78bd_642f);
+        let t = u128::from(self.state) * u128::from(self.state ^ 0xe703_7ed1_a0b4_28db);
+        (t >> 64) as u64 ^ t as u64
+    }
+}
+
+impl<V, C> Replacement<V, C> for WyRandReplacement
+where
+    C: Capacity,
+{
+    #[inline]
+    fn choose_for_replacement<'a>(
+        &mut self,
+        mut candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+    ) -> usize
+    where
+        V: 'a,
+    {
+        let len = candidates.len() as u64;
+        assert!(len > 0);
+        // Map the random number onto `0..len` with a widening multiply, and
+        // reject the few random numbers that would make some candidates more
+        // likely than others. This is Lemire's "nearly divisionless" method:
+        // the division to find the rejection threshold is only needed when the
+        // low bits of the product are small enough that rejection is possible.
+        let mut m = u128::from(self.next_u64()) * u128::from(len);
+        if (m as u64) < len {
+            let threshold = len.wrapping_neg() % len;
+            while (m as u64) < threshold {
+                m = u128::from(self.next_u64()) * u128::from(len);
+            }
+        }
+        candidates.nth((m >> 64) as usize).unwrap().0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Capacity4;
+
+    fn choices(policy: &mut WyRandReplacement, n: usize) -> Vec<usize> {
+        let values = [0; 4];
+        (0..n)
+            .map(|_| {
+                let candidates = values.iter().enumerate();
+                <WyRandReplacement as Replacement<usize, Capacity4>>::choose_for_replacement(
+                    policy, candidates,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn wyrand_is_reproducible() {
+        let mut original = WyRandReplacement::default();
+        let mut replay = WyRandReplacement::with_seed(original.seed());
+        assert_eq!(choices(&mut original, 100), choices(&mut replay, 100));
+
+        let mut other = WyRandReplacement::with_seed(original.seed().wrapping_add(1));
+        assert_ne!(choices(&mut original, 100), choices(&mut other, 100));
+    }
+
+    #[test]
+    fn wyrand_chooses_every_candidate() {
+        let mut policy = WyRandReplacement::with_seed(0);
+        let mut counts = [0; 4];
+        for i in choices(&mut policy, 4000) {
+            counts[i] += 1;
+        }
+        for &count in &counts {
+            assert!(count > 800 && count < 1200, "{:?}", counts);
+        }
+    }
+}
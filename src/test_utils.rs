@@ -0,0 +1,134 @@
+//! Helpers shared by this crate's unit tests.
+
+use super::*;
+
+/// A cache key that can be made from one of the `usize` keys in a trace.
+pub(crate) trait TraceKey: PartialEq {
+    fn from_trace(key: usize) -> Self;
+}
+
+impl TraceKey for usize {
+    fn from_trace(key: usize) -> Self {
+        key
+    }
+}
+
+impl TraceKey for u64 {
+    fn from_trace(key: usize) -> Self {
+        key as u64
+    }
+}
+
+impl TraceKey for *mut u8 {
+    fn from_trace(key: usize) -> Self {
+        key as *mut u8
+    }
+}
+
+/// A cache that a trace of keys can be run through.
+pub(crate) trait TraceCache {
+    /// Look up the `key`, and insert it if it is not found. Return `true` if
+    /// it was found.
+    fn access(&mut self, key: usize) -> bool;
+}
+
+impl<K, C, I, R> TraceCache for AssociativeCache<K, usize, C, I, R>
+where
+    K: TraceKey,
+    C: Capacity,
+    I: Indices<K, C>,
+    R: Replacement<usize, C>,
+{
+    fn access(&mut self, key: usize) -> bool {
+        if self.get(&K::from_trace(key)).is_some() {
+            return true;
+        }
+        self.insert(K::from_trace(key), key);
+        false
+    }
+}
+
+impl<K, C, I, R, A> TraceCache for AdmissionAssociativeCache<K, usize, C, I, R, A>
+where
+    K: TraceKey,
+    C: Capacity,
+    I: Indices<K, C>,
+    R: Clone + Replacement<usize, C>,
+    A: Admission<K, C>,
+{
+    fn access(&mut self, key: usize) -> bool {
+        if self.get(&K::from_trace(key)).is_some() {
+            return true;
+        }
+        let _ = self.insert(K::from_trace(key), key);
+        false
+    }
+}
+
+/// An `Indices` without a `Default` implementation that puts every key in the
+/// same two-way set, starting at the given slot.
+pub(crate) struct Fixed(pub(crate) usize);
+
+impl<K, C: Capacity> Indices<K, C> for Fixed {
+    type Indices = std::ops::Range<usize>;
+
+    fn indices(&self, _key: &K) -> Self::Indices {
+        self.0..self.0 + 2
+    }
+}
+
+/// A simple linear congruential generator, so that tests that need
+/// random-looking numbers are deterministic.
+pub(crate) struct Lcg(u64);
+
+impl Default for Lcg {
+    fn default() -> Self {
+        Lcg(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+impl Iterator for Lcg {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        Some((self.0 >> 33) as usize)
+    }
+}
+
+/// Run the `trace` of keys through the `cache`, inserting every key that
+/// misses, and return the number of hits.
+pub(crate) fn hits(cache: &mut impl TraceCache, trace: impl IntoIterator<Item = usize>) -> usize {
+    trace.into_iter().filter(|&key| cache.access(key)).count()
+}
+
+/// Call the `policy`'s `choose_for_replacement` directly, with the
+/// `candidates` slots holding `()` values.
+pub(crate) fn choose<C, R>(policy: &mut R, candidates: &[usize]) -> usize
+where
+    C: Capacity,
+    R: Replacement<(), C>,
+{
+    policy.choose_for_replacement(candidates.iter().map(|&index| (index, &())))
+}
+
+/// Call the `policy`'s `on_insert` directly, for slot `index` in `set`.
+pub(crate) fn insert<C, R>(policy: &mut R, index: usize, set: &[usize])
+where
+    C: Capacity,
+    R: Replacement<(), C>,
+{
+    policy.on_insert(index, set.iter().copied(), &());
+}
+
+/// Call the `policy`'s `on_hit` directly, for slot `index` in `set`.
+pub(crate) fn hit<C, R>(policy: &mut R, index: usize, set: &[usize])
+where
+    C: Capacity,
+    R: Replacement<(), C>,
+{
+    policy.on_hit(index, set.iter().copied(), &());
+}
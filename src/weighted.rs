@@ -0,0 +1,878 @@
+//! A weighted associative cache, where entries consume a variable amount of a
+//! total weight budget, in addition to a slot in the cache.
+
+use super::*;
+use std::fmt;
+
+/// A trait for cache values that consume a variable amount of a
+/// `WeightedAssociativeCache`'s budget, such as their size in bytes.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// struct Image {
+///     pixels: Vec<u32>,
+/// }
+///
+/// impl Weigh for Image {
+///     fn weight(&self) -> usize {
+///         self.pixels.len() * std::mem::size_of::<u32>()
+///     }
+/// }
+/// ```
+pub trait Weigh {
+    /// This value's weight, in any unit, as long as it is consistent across
+    /// all of the cache's values and its budget.
+    fn weight(&self) -> usize;
+}
+
+/// A fixed-size associative cache mapping `K` keys to `V` values, where every
+/// entry also consumes its value's weight out of a total weight budget.
+///
+/// Entries are placed exactly like they are in an `AssociativeCache` with the
+/// same `C`, `I`, and `R` type parameters. When inserting an entry would exceed
+/// the budget, additional entries are evicted from the new entry's set (as
+/// chosen by the replacement policy) until it fits, and every evicted entry is
+/// returned.
+///
+/// If the new entry cannot fit even after evicting every other entry in its
+/// set, because it weighs more than the budget or because too much of the
+/// budget is consumed by entries in other sets, then the cache is unchanged and
+/// the new entry is returned as an error.
+///
+/// Values are weighed once, when they are inserted. If a value is mutated in
+/// place, for example via `get_mut`, it keeps consuming its original weight.
+///
+/// ## Example
+///
+/// ```
+/// use associative_cache::*;
+///
+/// #[derive(Debug)]
+/// struct Blob(Vec<u8>);
+///
+/// impl Weigh for Blob {
+///     fn weight(&self) -> usize {
+///         self.0.len()
+///     }
+/// }
+///
+/// // A four-way associative cache with up to 1 MiB of blobs.
+/// let mut cache = WeightedAssociativeCache::<
+///     String,
+///     Blob,
+///     Capacity64,
+///     HashFourWay,
+///     RoundRobinReplacement,
+/// >::with_budget(1 << 20);
+///
+/// let evicted = cache.insert("small".to_string(), Blob(vec![0; 1 << 10]));
+/// assert!(evicted.unwrap().is_empty());
+/// assert_eq!(cache.weight(), 1 << 10);
+/// ```
+#[derive(Debug)]
+pub struct WeightedAssociativeCache<K, V, C, I, R>
+where
+    C: Capacity,
+    R: Replacement<V, C>,
+{
+    cache: AssociativeCache<K, V, C, I, R>,
+    // The weight of each slot's entry, when it was inserted.
+    weights: Vec<usize>,
+    weight: usize,
+    budget: usize,
+}
+
+impl<K, V, C, I, R> WeightedAssociativeCache<K, V, C, I, R>
+where
+    C: Capacity,
+    R: Replacement<V, C>,
+{
+    /// Construct a `WeightedAssociativeCache` with the given weight budget and
+    /// the default replacement policy.
+    pub fn with_budget(budget: usize) -> Self
+    where
+        I: Default,
+        R: Default,
+    {
+        Self::with_budget_and_replacement_policy(budget, R::default())
+    }
+
+    /// Construct a `WeightedAssociativeCache` with the given weight budget and
+    /// replacement policy.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// struct Bytes(Vec<u8>);
+    ///
+    /// impl Weigh for Bytes {
+    ///     fn weight(&self) -> usize {
+    ///         self.0.len()
+    ///     }
+    /// }
+    ///
+    /// let cache = WeightedAssociativeCache::<
+    ///     String,
+    ///     Bytes,
+    ///     Capacity128,
+    ///     HashEightWay,
+    ///     _,
+    /// >::with_budget_and_replacement_policy(4096, LfuReplacement::default());
+    /// ```
+    pub fn with_budget_and_replacement_policy(budget: usize, replacement_policy: R) -> Self
+    where
+        I: Default,
+    {
+        Self::with_indices_budget_and_replacement_policy(I::default(), budget, replacement_policy)
+    }
+
+    /// Construct a `WeightedAssociativeCache` with the given `Indices`, weight
+    /// budget, and the default replacement policy.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// struct Bytes(Vec<u8>);
+    ///
+    /// impl Weigh for Bytes {
+    ///     fn weight(&self) -> usize {
+    ///         self.0.len()
+    ///     }
+    /// }
+    ///
+    /// let cache = WeightedAssociativeCache::<
+    ///     String,
+    ///     Bytes,
+    ///     Capacity128,
+    ///     HashEightWayWith,
+    ///     RoundRobinReplacement,
+    /// >::with_indices_and_budget(HashEightWayWith::with_hasher(RandomState::new()), 4096);
+    /// ```
+    pub fn with_indices_and_budget(indices: I, budget: usize) -> Self
+    where
+        R: Default,
+    {
+        Self::with_indices_budget_and_replacement_policy(indices, budget, R::default())
+    }
+
+    /// Construct a `WeightedAssociativeCache` with the given `Indices`, weight
+    /// budget, and replacement policy.
+    pub fn with_indices_budget_and_replacement_policy(
+        indices: I,
+        budget: usize,
+        replacement_policy: R,
+    ) -> Self {
+        WeightedAssociativeCache {
+            cache: AssociativeCache::with_indices_and_replacement_policy(
+                indices,
+                replacement_policy,
+            ),
+            weights: vec![0; C::CAPACITY],
+            weight: 0,
+            budget,
+        }
+    }
+
+    /// Get a shared reference to this cache's `Indices`.
+    #[inline]
+    pub fn indices(&self) -> &I {
+        self.cache.indices()
+    }
+
+    /// Get a shared reference to this cache's replacement policy.
+    #[inline]
+    pub fn replacement_policy(&self) -> &R {
+        self.cache.replacement_policy()
+    }
+
+    /// Get an exclusive reference to this cache's replacement policy.
+    #[inline]
+    pub fn replacement_policy_mut(&mut self) -> &mut R {
+        self.cache.replacement_policy_mut()
+    }
+
+    /// Get this cache's constant capacity, aka `C::CAPACITY`.
+    ///
+    /// This is the maximum number of entries, regardless of their weight.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.cache.capacity()
+    }
+
+    /// Get this cache's weight budget.
+    #[inline]
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Get the total weight of the entries in this cache.
+    ///
+    /// This is always less than or equal to the budget.
+    #[inline]
+    pub fn weight(&self) -> usize {
+        debug_assert!(self.weight <= self.budget);
+        self.weight
+    }
+
+    /// Get the number of entries in this cache.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Return `true` if there are zero entries in the cache.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Insert a new entry into the cache, returning every entry that is no
+    /// longer in the cache as a result.
+    ///
+    /// This includes the old entry for this key, if any, and any entries that
+    /// were evicted to make room for the new entry. Replacing the old entry for
+    /// this key is an update for the replacement policy, rather than a removal
+    /// and an insertion.
+    ///
+    /// If the new entry cannot fit within the budget, even counting the old
+    /// entry for this key as freed, then the cache is unchanged and the new
+    /// entry is returned as an error.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// #[derive(Debug)]
+    /// struct Blob(Vec<u8>);
+    ///
+    /// impl Weigh for Blob {
+    ///     fn weight(&self) -> usize {
+    ///         self.0.len()
+    ///     }
+    /// }
+    ///
+    /// let mut cache = WeightedAssociativeCache::<
+    ///     String,
+    ///     Blob,
+    ///     Capacity4,
+    ///     HashFourWay,
+    ///     RoundRobinReplacement,
+    /// >::with_budget(10);
+    ///
+    /// cache.insert("a".to_string(), Blob(vec![0; 4]));
+    /// cache.insert("b".to_string(), Blob(vec![0; 4]));
+    ///
+    /// // There are empty slots left, but not enough of the budget, so both of
+    /// // the other entries are evicted.
+    /// let evicted = cache.insert("c".to_string(), Blob(vec![0; 8])).unwrap();
+    /// assert_eq!(evicted.len(), 2);
+    /// assert_eq!(cache.len(), 1);
+    ///
+    /// // This entry could never fit, so it is handed right back.
+    /// let rejected = cache.insert("d".to_string(), Blob(vec![0; 11]));
+    /// assert_eq!(rejected.unwrap_err().0, "d");
+    /// assert!(cache.get("d").is_none());
+    /// ```
+    pub fn insert(&mut self, key: K, value: V) -> Result<Vec<(K, V)>, (K, V)>
+    where
+        I: Indices<K, C>,
+        K: PartialEq,
+        V: Weigh,
+    {
+        let mut evicted = vec![];
+        let set = self.cache.indices.indices(&key);
+        let old = set
+            .clone()
+            .find(|&index| matches!(&self.cache.entries[index], Some((k, _)) if *k == key));
+        self.insert_evicting(key, value, old, &mut evicted)?;
+        Ok(evicted)
+    }
+
+    /// Insert an entry, updating the old entry for its key at the slot `old`,
+    /// if any, and pushing every evicted entry onto `evicted`. Return the new
+    /// entry's index, or the new entry itself if it cannot fit.
+    fn insert_evicting(
+        &mut self,
+        key: K,
+        value: V,
+        old: Option<usize>,
+        evicted: &mut Vec<(K, V)>,
+    ) -> Result<usize, (K, V)>
+    where
+        I: Indices<K, C>,
+        V: Weigh,
+    {
+        let capacity = self.capacity();
+        let weight = value.weight();
+        let set = self.cache.indices.indices(&key);
+
+        // Even evicting every entry in the set might not free up enough of the
+        // budget, in which case we don't evict anything.
+        let set_weight: usize = set
+            .clone()
+            .filter(|&index| {
+                assert!(
+                    index < capacity,
+                    "`Indices::indices` must always yield indices within the capacity"
+                );
+                self.cache.entries[index].is_some()
+            })
+            .map(|index| self.weights[index])
+            .sum();
+        // The old entry for this key, if any, is in the set, so its weight is
+        // counted as freed here.
+        if weight > self.budget || self.weight - set_weight > self.budget - weight {
+            return Err((key, value));
+        }
+
+        if let Some(index) = old {
+            let old = self.cache.entries[index].replace((key, value));
+            if let (Some((_, old)), Some((_, new))) = (&old, &self.cache.entries[index]) {
+                self.cache
+                    .replacement_policy
+                    .on_update(index, set.clone(), old, new);
+            }
+            evicted.extend(old);
+            self.weight = self.weight - self.weights[index] + weight;
+            self.weights[index] = weight;
+
+            // The new value may weigh more than the old one, so other entries
+            // in the set may still need to be evicted.
+            while self.weight > self.budget {
+                let victim = self.choose_for_replacement(set.clone(), Some(index));
+                evicted.push(self.remove_index(victim, set.clone()));
+            }
+            return Ok(index);
+        }
+
+        loop {
+            // Like `AssociativeCache::insert`, fill the last empty slot first.
+            let empty = set
+                .clone()
+                .filter(|&index| self.cache.entries[index].is_none())
+                .last();
+            let fits = self.weight + weight <= self.budget;
+            match (empty, fits) {
+                (Some(index), true) => {
+                    self.cache.entries[index] = Some((key, value));
+                    self.cache.len += 1;
+                    self.weights[index] = weight;
+                    self.weight += weight;
+                    let value = &self.cache.entries[index].as_ref().unwrap().1;
+                    self.cache.replacement_policy.on_insert(index, set, value);
+                    return Ok(index);
+                }
+                (None, true) => {
+                    let index = self.choose_for_replacement(set.clone(), None);
+                    self.weight = self.weight - self.weights[index] + weight;
+                    self.weights[index] = weight;
+                    evicted.extend(self.cache.replace_entry(index, set, key, value));
+                    return Ok(index);
+                }
+                (_, false) => {
+                    let index = self.choose_for_replacement(set.clone(), None);
+                    evicted.push(self.remove_index(index, set.clone()));
+                }
+            }
+        }
+    }
+
+    /// Let the replacement policy choose which entry to evict, among every
+    /// occupied slot in the `set` other than `except`.
+    fn choose_for_replacement(
+        &mut self,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+        except: Option<usize>,
+    ) -> usize {
+        let AssociativeCache {
+            ref entries,
+            ref mut replacement_policy,
+            ..
+        } = self.cache;
+        let candidates: Vec<_> = set
+            .clone()
+            .filter(|&index| Some(index) != except)
+            .filter_map(|index| entries[index].as_ref().map(|(_, v)| (index, v)))
+            .collect();
+        let index = replacement_policy.choose_for_replacement(candidates.into_iter());
+        debug_assert!(
+            set.clone().any(|i| i == index),
+            "`ReplacementPolicy::choose_for_replacement` must return a candidate index"
+        );
+        assert!(self.cache.entries[index].is_some());
+        index
+    }
+
+    /// Remove the entry at the occupied slot `index`, notify the replacement
+    /// policy, and return the entry.
+    fn remove_index(
+        &mut self,
+        index: usize,
+        set: impl ExactSizeIterator<Item = usize> + Clone,
+    ) -> (K, V) {
+        let (k, v) = self.cache.entries[index].take().unwrap();
+        self.cache.len -= 1;
+        self.weight -= self.weights[index];
+        self.weights[index] = 0;
+        self.cache.replacement_policy.on_remove(index, set, &v);
+        (k, v)
+    }
+
+    /// Get a shared reference to the value for a given key, if it exists in the
+    /// cache.
+    ///
+    /// This counts as a hit for the replacement policy. Use `peek` to look up a
+    /// value without notifying the replacement policy.
+    #[inline]
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        I: Indices<Q, C>,
+        Q: ?Sized + PartialEq,
+    {
+        self.cache.get(key)
+    }
+
+    /// Get a shared reference to the value for a given key, if it exists in the
+    /// cache, without notifying the replacement policy.
+    #[inline]
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        I: Indices<Q, C>,
+        Q: ?Sized + PartialEq,
+    {
+        self.cache.peek(key)
+    }
+
+    /// Get an exclusive reference to the value for a given key, if it exists in
+    /// the cache.
+    ///
+    /// The value keeps consuming the weight that it had when it was inserted,
+    /// regardless of how it is mutated.
+    #[inline]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        I: Indices<Q, C>,
+        Q: ?Sized + PartialEq,
+    {
+        self.cache.get_mut(key)
+    }
+
+    /// Remove an entry from the cache.
+    ///
+    /// If an entry for the key existed in the cache, it is removed and `Some`
+    /// is returned. Otherwise, `None` is returned.
+    #[inline]
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        I: Indices<Q, C>,
+        Q: ?Sized + PartialEq,
+    {
+        let set = self.cache.indices.indices(key);
+        let index = set.clone().find(
+            |&index| matches!(&self.cache.entries[index], Some((k, _)) if k.borrow() == key),
+        )?;
+        Some(self.remove_index(index, set).1)
+    }
+
+    /// Retain only the cache entries specified by the predicate.
+    ///
+    /// Calls `f` with each entry in the cache, and removes all entries where
+    /// `f` returned false.
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool)
+    where
+        I: Indices<K, C>,
+    {
+        for index in 0..self.capacity() {
+            if let Some((k, v)) = &mut self.cache.entries[index] {
+                if !f(k, v) {
+                    let set = self.cache.indices.indices(k);
+                    self.remove_index(index, set);
+                }
+            }
+        }
+    }
+
+    /// Remove all entries from the cache.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        for w in &mut self.weights {
+            *w = 0;
+        }
+        self.weight = 0;
+    }
+
+    /// Get the key's corresponding slot within the cache for get-or-create
+    /// operations.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use associative_cache::*;
+    ///
+    /// #[derive(Debug)]
+    /// struct Text(String);
+    ///
+    /// impl Weigh for Text {
+    ///     fn weight(&self) -> usize {
+    ///         self.0.len()
+    ///     }
+    /// }
+    ///
+    /// let mut cache = WeightedAssociativeCache::<
+    ///     String,
+    ///     Text,
+    ///     Capacity4,
+    ///     HashTwoWay,
+    ///     RoundRobinReplacement,
+    /// >::with_budget(100);
+    ///
+    /// let (val, evicted) = cache
+    ///     .entry("hi")
+    ///     .or_insert_with(|| "hi".to_string(), || Text("hello".to_string()))
+    ///     .unwrap();
+    /// assert_eq!(val.0, "hello");
+    /// assert!(evicted.is_empty());
+    /// ```
+    #[inline]
+    pub fn entry<Q>(&mut self, key: &Q) -> WeightedEntry<'_, K, V, C, I, R>
+    where
+        K: Borrow<Q>,
+        I: Indices<Q, C>,
+        Q: ?Sized + PartialEq,
+    {
+        let set = self.cache.indices.indices(key);
+        let index = set
+            .clone()
+            .find(|&index| matches!(&self.cache.entries[index], Some((k, _)) if k.borrow() == key));
+        if let Some(index) = index {
+            let value = &self.cache.entries[index].as_ref().unwrap().1;
+            self.cache.replacement_policy.on_hit(index, set, value);
+        }
+        WeightedEntry { cache: self, index }
+    }
+
+    /// Iterate over shared references to this cache's keys and values.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.cache.iter()
+    }
+
+    /// Iterate over shared references to this cache's keys and exclusive
+    /// references to its values.
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        self.cache.iter_mut()
+    }
+
+    /// Consume this cache, and iterate over its keys and values.
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    pub fn into_iter(self) -> IntoIter<K, V> {
+        self.cache.into_iter()
+    }
+}
+
+/// A potentially-empty entry in a weighted cache, used to perform
+/// get-or-create operations on the cache.
+///
+/// Constructed via the `WeightedAssociativeCache::entry` method.
+pub struct WeightedEntry<'a, K, V, C, I, R>
+where
+    C: Capacity,
+    R: Replacement<V, C>,
+{
+    cache: &'a mut WeightedAssociativeCache<K, V, C, I, R>,
+    // The index of the existing entry for this key, if any.
+    index: Option<usize>,
+}
+
+impl<'a, K, V, C, I, R> fmt::Debug for WeightedEntry<'a, K, V, C, I, R>
+where
+    C: Capacity,
+    R: Replacement<V, C>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WeightedEntry")
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl<'a, K, V, C, I, R> WeightedEntry<'a, K, V, C, I, R>
+where
+    C: Capacity,
+    I: Indices<K, C>,
+    R: Replacement<V, C>,
+    V: Weigh,
+{
+    /// Get the underlying cached data, creating and inserting it into the cache
+    /// if it doesn't already exist, along with every entry that was evicted to
+    /// make room for it.
+    ///
+    /// If the new entry cannot fit within the budget, then the cache is
+    /// unchanged and the new entry is returned as an error.
+    ///
+    /// Like `Entry::or_insert_with`, **`make_key` must return an owned key that
+    /// is equivalent to the borrowed key that was used to get this
+    /// `WeightedEntry`.**
+    #[inline]
+    #[allow(clippy::type_complexity)]
+    pub fn or_insert_with(
+        self,
+        make_key: impl FnOnce() -> K,
+        make_val: impl FnOnce() -> V,
+    ) -> Result<(&'a mut V, Vec<(K, V)>), (K, V)> {
+        let mut evicted = vec![];
+        let index = match self.index {
+            Some(index) => index,
+            None => self
+                .cache
+                .insert_evicting(make_key(), make_val(), None, &mut evicted)?,
+        };
+        match &mut self.cache.cache.entries[index] {
+            Some((_, v)) => Ok((v, evicted)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::zero_ptr)]
+mod tests {
+    use super::*;
+    use crate::test_utils::Fixed;
+    use crate::{Capacity8, FifoReplacement, PointerFourWay};
+
+    #[derive(Debug, PartialEq)]
+    struct Blob(usize);
+
+    impl Weigh for Blob {
+        fn weight(&self) -> usize {
+            self.0
+        }
+    }
+
+    type Cache =
+        WeightedAssociativeCache<*mut u8, Blob, Capacity8, PointerFourWay, FifoReplacement>;
+
+    #[test]
+    fn insert_evicts_until_fits() {
+        let mut cache = Cache::with_budget(10);
+
+        // Even keys go in the first set, and odd keys in the second.
+        assert_eq!(cache.insert(0 as *mut _, Blob(3)), Ok(vec![]));
+        assert_eq!(cache.insert(2 as *mut _, Blob(3)), Ok(vec![]));
+        assert_eq!(cache.insert(1 as *mut _, Blob(3)), Ok(vec![]));
+        assert_eq!(cache.weight(), 9);
+
+        // Evicts the oldest entries in its own set, even though there are
+        // empty slots.
+        assert_eq!(
+            cache.insert(4 as *mut _, Blob(7)),
+            Ok(vec![(0 as *mut _, Blob(3)), (2 as *mut _, Blob(3))])
+        );
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.weight(), 10);
+
+        // Can't fit without evicting from the other set.
+        assert_eq!(
+            cache.insert(6 as *mut _, Blob(8)),
+            Err((6 as *mut _, Blob(8)))
+        );
+        assert_eq!(cache.weight(), 10);
+
+        // Re-inserting a key returns its old entry.
+        assert_eq!(
+            cache.insert(1 as *mut _, Blob(1)),
+            Ok(vec![(1 as *mut _, Blob(3))])
+        );
+        assert_eq!(cache.weight(), 8);
+    }
+
+    #[test]
+    fn insert_into_full_set() {
+        let mut cache = Cache::with_budget(100);
+        for i in [0, 2, 4, 6] {
+            assert_eq!(cache.insert(i as *mut _, Blob(1)), Ok(vec![]));
+        }
+        assert_eq!(
+            cache.insert(8 as *mut _, Blob(5)),
+            Ok(vec![(0 as *mut _, Blob(1))])
+        );
+        assert_eq!(cache.weight(), 8);
+    }
+
+    #[test]
+    fn reinsert_oversized_value() {
+        let mut cache = Cache::with_budget(10);
+        assert_eq!(cache.insert(0 as *mut _, Blob(3)), Ok(vec![]));
+        assert_eq!(cache.insert(1 as *mut _, Blob(3)), Ok(vec![]));
+
+        // The new value doesn't fit even without the old one, so the old
+        // entry is kept.
+        assert_eq!(
+            cache.insert(0 as *mut _, Blob(8)),
+            Err((0 as *mut _, Blob(8)))
+        );
+        assert_eq!(cache.get(&(0 as *mut _)), Some(&Blob(3)));
+        assert_eq!(cache.weight(), 6);
+
+        // But it fits when the old entry's weight is freed.
+        assert_eq!(
+            cache.insert(0 as *mut _, Blob(7)),
+            Ok(vec![(0 as *mut _, Blob(3))])
+        );
+        assert_eq!(cache.get(&(0 as *mut _)), Some(&Blob(7)));
+        assert_eq!(cache.weight(), 10);
+    }
+
+    #[test]
+    fn reinsert_is_an_update() {
+        // Records which hooks are called, and always replaces the first
+        // candidate.
+        #[derive(Default)]
+        struct Hooks(Vec<(&'static str, usize)>);
+
+        impl<V, C: Capacity> Replacement<V, C> for Hooks {
+            fn choose_for_replacement<'a>(
+                &mut self,
+                mut candidates: impl ExactSizeIterator<Item = (usize, &'a V)>,
+            ) -> usize
+            where
+                V: 'a,
+            {
+                candidates.next().unwrap().0
+            }
+
+            fn on_insert(
+                &mut self,
+                index: usize,
+                _: impl ExactSizeIterator<Item = usize> + Clone,
+                _: &V,
+            ) {
+                self.0.push(("insert", index));
+            }
+
+            fn on_update(
+                &mut self,
+                index: usize,
+                _: impl ExactSizeIterator<Item = usize> + Clone,
+                _: &V,
+                _: &V,
+            ) {
+                self.0.push(("update", index));
+            }
+
+            fn on_remove(
+                &mut self,
+                index: usize,
+                _: impl ExactSizeIterator<Item = usize> + Clone,
+                _: &V,
+            ) {
+                self.0.push(("remove", index));
+            }
+        }
+
+        let mut cache = WeightedAssociativeCache::<
+            *mut u8,
+            Blob,
+            Capacity8,
+            PointerFourWay,
+            Hooks,
+        >::with_budget(10);
+        assert_eq!(cache.insert(0 as *mut _, Blob(3)), Ok(vec![]));
+        assert_eq!(cache.insert(2 as *mut _, Blob(3)), Ok(vec![]));
+
+        // The heavier value is updated in place, and then other entries in its
+        // set are evicted until it fits.
+        assert_eq!(
+            cache.insert(0 as *mut _, Blob(8)),
+            Ok(vec![(0 as *mut _, Blob(3)), (2 as *mut _, Blob(3))])
+        );
+        assert_eq!(cache.get(&(0 as *mut _)), Some(&Blob(8)));
+        assert_eq!(cache.weight(), 8);
+        assert_eq!(
+            cache.replacement_policy().0,
+            vec![("insert", 3), ("insert", 2), ("update", 3), ("remove", 2)]
+        );
+    }
+
+    #[test]
+    fn remove_retain_and_clear() {
+        let mut cache = Cache::with_budget(100);
+        for i in 0..8 {
+            assert_eq!(cache.insert(i as *mut _, Blob(i)), Ok(vec![]));
+        }
+        assert_eq!(cache.weight(), 28);
+
+        assert_eq!(cache.remove(&(7 as *mut _)), Some(Blob(7)));
+        assert_eq!(cache.weight(), 21);
+
+        cache.retain(|_, v| v.0 % 2 == 0);
+        assert_eq!(cache.weight(), 12);
+        assert_eq!(cache.len(), 4);
+
+        cache.clear();
+        assert_eq!(cache.weight(), 0);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn entry() {
+        let mut cache = Cache::with_budget(10);
+        assert_eq!(cache.insert(0 as *mut _, Blob(6)), Ok(vec![]));
+
+        assert_eq!(
+            cache
+                .entry(&(0 as *mut _))
+                .or_insert_with(|| 0 as *mut _, || unreachable!()),
+            Ok((&mut Blob(6), vec![]))
+        );
+        assert_eq!(
+            cache
+                .entry(&(2 as *mut _))
+                .or_insert_with(|| 2 as *mut _, || Blob(5)),
+            Ok((&mut Blob(5), vec![(0 as *mut _, Blob(6))]))
+        );
+        assert_eq!(
+            cache
+                .entry(&(4 as *mut _))
+                .or_insert_with(|| 4 as *mut _, || Blob(11)),
+            Err((4 as *mut _, Blob(11)))
+        );
+        assert_eq!(cache.weight(), 5);
+    }
+
+    #[test]
+    fn with_indices() {
+        let mut cache = WeightedAssociativeCache::<
+            *mut u8,
+            Blob,
+            Capacity8,
+            Fixed,
+            FifoReplacement,
+        >::with_indices_and_budget(Fixed(4), 100);
+        assert_eq!(cache.indices().0, 4);
+
+        assert_eq!(cache.insert(0 as *mut _, Blob(1)), Ok(vec![]));
+        assert_eq!(cache.insert(1 as *mut _, Blob(1)), Ok(vec![]));
+        assert_eq!(
+            cache.insert(2 as *mut _, Blob(1)),
+            Ok(vec![(0 as *mut _, Blob(1))])
+        );
+        assert_eq!(cache.len(), 2);
+    }
+}